@@ -0,0 +1,186 @@
+/*
+ * Source-compatible with the fddma driver of the Phytium standalone SDK, not
+ * ABI-compatible: error values and structure layouts differ from the SDK.
+ * Rebuild C code against this header, do not mix it with SDK objects.
+ */
+
+#ifndef FDDMA_H
+#define FDDMA_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit. */
+
+#include <stdint.h>
+
+// Success
+#define FDDMA_SUCCESS 0
+
+// The instance is not initialized or an argument is null
+#define FDDMA_ERR_NOT_INIT 1
+
+// The channel is already configured
+#define FDDMA_ERR_CHAN_BINDED 2
+
+// The channel is running
+#define FDDMA_ERR_CHAN_RUNNING 3
+
+// Transfer size is not a multiple of 4 bytes
+#define FDDMA_ERR_INVALID_TRANS_SIZE 4
+
+// Wait for the hardware timed out
+#define FDDMA_ERR_WAIT_TIMEOUT 5
+
+// DDR address is not aligned to 4 bytes
+#define FDDMA_ERR_INVALID_DDR_ADDR 6
+
+// The instance is already initialized
+#define FDDMA_ERR_IS_USED 7
+
+// Channel index, slave ID, direction or device address is out of range
+#define FDDMA_ERR_INVALID_INPUT 8
+
+// Device to memory
+#define FDDMA_CHAN_REQ_RX 0
+
+// Memory to device
+#define FDDMA_CHAN_REQ_TX 1
+
+// The channel finished its transfer
+#define FDDMA_CHAN_EVT_REQ_DONE 0
+
+// Controller configuration
+typedef struct FDdmaConfig {
+  // Controller index
+  uint32_t id;
+  // Mapped base address of the controller registers
+  uintptr_t base_addr;
+  // Interrupt number
+  uint32_t irq_num;
+  // Interrupt priority
+  uint32_t irq_prority;
+} FDdmaConfig;
+
+// Controller instance, allocated by the caller
+typedef struct FDdma {
+  // Configuration given to `FDdmaCfgInitialize`
+  struct FDdmaConfig config;
+  // Non-zero once initialized
+  uint32_t is_ready;
+  // Driver state, owned by this crate
+  void *priv_;
+} FDdma;
+
+// SDK error code
+typedef uint32_t FError;
+
+// Transfer direction of a channel (`FDdmaChanRequst`)
+typedef uint32_t FDdmaChanRequst;
+
+// Channel configuration
+typedef struct FDdmaChanConfig {
+  // Peripheral request line
+  uint32_t slave_id;
+  // `FDDMA_CHAN_REQ_RX` or `FDDMA_CHAN_REQ_TX`
+  FDdmaChanRequst req_mode;
+  // DDR bus address, source for TX and destination for RX
+  uintptr_t ddr_addr;
+  // Peripheral FIFO address
+  uint32_t dev_addr;
+  // Transfer length in bytes
+  uint32_t trans_len;
+  // Hardware timeout count, 0 disables the timeout
+  uint32_t timeout;
+} FDdmaChanConfig;
+
+// Channel event (`FDdmaChanEvt`)
+typedef uint32_t FDdmaChanEvt;
+
+// Channel event callback, called from `FDdmaIrqHandler` with the channel
+// index and the registered argument
+typedef void (*FDdmaChanEvtHandler)(uint32_t chan_idx, void *args);
+
+// Initialize `instance` for the controller described by `config`, resetting
+// the controller
+//
+// # Safety
+//
+// `instance` must point to a valid `FDdma` and `config` to a valid
+// `FDdmaConfig` whose `base_addr` maps the controller registers.
+FError FDdmaCfgInitialize(struct FDdma *instance, const struct FDdmaConfig *config);
+
+// Release all channels of `instance` and stop the controller
+//
+// # Safety
+//
+// `instance` must be null or point to a valid `FDdma`, and no other call on
+// it may run concurrently.
+void FDdmaDeInitialize(struct FDdma *instance);
+
+// Bind channel `chan_idx` to a peripheral and program its transfer
+//
+// # Safety
+//
+// `instance` must be null or point to a valid `FDdma`, `config` must be null
+// or point to a valid `FDdmaChanConfig`.
+FError FDdmaChanConfigure(struct FDdma *instance,
+                          uint32_t chan_idx,
+                          const struct FDdmaChanConfig *config);
+
+// Unbind channel `chan_idx`, stopping it if running
+//
+// # Safety
+//
+// `instance` must be null or point to a valid `FDdma`.
+FError FDdmaChanDeconfigure(struct FDdma *instance, uint32_t chan_idx);
+
+// Start the transfer of channel `chan_idx`
+//
+// # Safety
+//
+// `instance` must be null or point to a valid `FDdma`.
+FError FDdmaChanActive(struct FDdma *instance, uint32_t chan_idx);
+
+// Stop the transfer of channel `chan_idx`
+//
+// # Safety
+//
+// `instance` must be null or point to a valid `FDdma`.
+FError FDdmaChanDeactive(struct FDdma *instance, uint32_t chan_idx);
+
+// Enable the controller
+//
+// # Safety
+//
+// `instance` must be null or point to a valid `FDdma`.
+FError FDdmaStart(struct FDdma *instance);
+
+// Disable the controller
+//
+// # Safety
+//
+// `instance` must be null or point to a valid `FDdma`.
+FError FDdmaStop(struct FDdma *instance);
+
+// Register `handler` for event `evt` of channel `chan_idx`
+//
+// # Safety
+//
+// `instance` must be null or point to a valid `FDdma`, `handler` must be
+// safe to call from interrupt context with `handler_arg`.
+void FDdmaRegisterChanEvtHandler(struct FDdma *instance,
+                                 uint32_t chan_idx,
+                                 FDdmaChanEvt evt,
+                                 FDdmaChanEvtHandler handler,
+                                 void *handler_arg);
+
+// Interrupt handler, `args` being the `FDdma` instance
+//
+// Completed channels are stopped and their `FDDMA_CHAN_EVT_REQ_DONE`
+// handler called.
+//
+// # Safety
+//
+// `args` must be null or point to a valid `FDdma`, and channel calls must
+// not run concurrently on another core, as with the SDK driver.
+void FDdmaIrqHandler(int32_t _vector, void *args);
+
+#endif  /* FDDMA_H */
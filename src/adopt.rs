@@ -0,0 +1,107 @@
+//! Taking over channels configured before the driver, e.g. by U-Boot
+//!
+//! The channels bound in the hardware and not held by a `Channel` of this
+//! driver can be listed with their programming, and adopted as `Channel`
+//! handles without touching the registers, so a console transfer started by
+//! the bootloader keeps running across the hand-over. `DDMA::reset` must not
+//! be called before, as it unbinds all channels.
+
+use log::trace;
+use tock_registers::interfaces::Readable;
+
+use crate::{
+    Channel, DDMA, DdmaError, DmaDirection,
+    reg::{DMA_CHALX_CTL, DdmaRegister},
+};
+
+/// Programming of a channel found in the hardware
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelState {
+    /// Channel number (0-7)
+    pub channel: u8,
+    /// Peripheral request line the channel is bound to
+    pub slave_id: u8,
+    pub direction: DmaDirection,
+    /// Bus address of the memory region, as programmed
+    pub mem_addr: u64,
+    /// Device address, as programmed
+    pub dev_addr: u32,
+    /// Size of the current block in bytes
+    pub size: usize,
+    /// Bytes moved so far by the current block
+    pub transferred: usize,
+    /// The channel is enabled
+    pub running: bool,
+    /// The completion interrupt of the channel is unmasked
+    pub irq: bool,
+}
+
+impl DDMA {
+    /// Read the programming of channel `n`, if it is bound to a peripheral
+    pub fn channel_state(&self, n: u8) -> Option<ChannelState> {
+        let channel = n as usize;
+        if !self.reg().is_channel_bind(channel) {
+            return None;
+        }
+
+        let reg = unsafe { self.channel_reg(n).as_ref() };
+        let (sel, _) = self.reg().channel_config(channel);
+        let mem_addr = ((reg.ddr_upaddr.get() as u64) << 32) | reg.ddr_lwaddr.get() as u64;
+        let current = ((reg.crt_upaddr.get() as u64) << 32) | reg.crt_lwaddr.get() as u64;
+        let size = reg.ts.get() as usize;
+        let direction = match reg.ctl.read_as_enum(DMA_CHALX_CTL::CHALX_MODE) {
+            Some(DMA_CHALX_CTL::CHALX_MODE::Value::Rx) => DmaDirection::DeviceToMemory,
+            _ => DmaDirection::MemoryToDevice,
+        };
+
+        Some(ChannelState {
+            channel: n,
+            slave_id: sel as u8,
+            direction,
+            mem_addr,
+            dev_addr: reg.dev_addr.get(),
+            size,
+            transferred: (current.saturating_sub(mem_addr) as usize).min(size),
+            running: reg.ctl.is_set(DMA_CHALX_CTL::CHALX_EN),
+            irq: !self.reg().is_channel_interrupt_masked(channel),
+        })
+    }
+
+    /// Channels bound in the hardware that no `Channel` of this driver holds
+    pub fn bound_channels(&self) -> impl Iterator<Item = ChannelState> + '_ {
+        (0..DdmaRegister::MAX_CHANNELS as u8)
+            .filter(|&n| !self.shared.is_owned(n))
+            .filter_map(|n| self.channel_state(n))
+    }
+
+    /// Take over channel `n`, bound before the driver, as a `Channel`
+    ///
+    /// The channel keeps its binding, interrupt mask and programming, and a
+    /// running transfer is not interrupted: its completion is reported by the
+    /// returned channel. The channel has no driver-owned buffer. The memory
+    /// address is kept as the bus address found in the hardware, so adoption
+    /// is refused with `InvalidAddress` on a controller with an
+    /// `AddressTranslation`, which would translate it again.
+    pub fn adopt_channel(&self, n: u8) -> Result<Channel, DdmaError> {
+        if n as usize >= DdmaRegister::MAX_CHANNELS {
+            return Err(DdmaError::InvalidChannel);
+        }
+        if self.shared.has_translation() {
+            trace!("Channel {} cannot be adopted through a translation", n);
+            return Err(DdmaError::InvalidAddress);
+        }
+
+        let _guard = self.shared.lock.lock();
+        if self.shared.is_owned(n) {
+            return Err(DdmaError::ChannelInUse);
+        }
+        if !self.reg().is_channel_bind(n as usize) {
+            return Err(DdmaError::NotBound);
+        }
+
+        self.shared.claim(n);
+        let channel = Channel::adopt(self.channel_reg(n), self.reg, n, self.shared.clone());
+        trace!("Channel {} adopted, {} bytes", n, channel.capacity());
+        Ok(channel)
+    }
+}
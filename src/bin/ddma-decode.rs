@@ -0,0 +1,135 @@
+//! Decode dumps of the DDMA register window
+//!
+//! ```text
+//! ddma-decode [--binary] DUMP
+//! ddma-decode [--binary] --diff OLD NEW
+//! ```
+//!
+//! A dump is hex text (32-bit words, optionally prefixed by an address as
+//! printed by U-Boot `md.l`) or a binary file of little-endian words.
+
+use std::{env, fs, process::ExitCode};
+
+use phytium_ddma::decode::{CHANNELS, Dump, Register, channel_offset};
+
+fn usage() -> ExitCode {
+    eprintln!("usage: ddma-decode [--binary] DUMP");
+    eprintln!("       ddma-decode [--binary] --diff OLD NEW");
+    ExitCode::FAILURE
+}
+
+fn load(path: &str, binary: bool) -> Result<Dump, String> {
+    let bytes = fs::read(path).map_err(|e| format!("{path}: {e}"))?;
+    let text = match std::str::from_utf8(&bytes) {
+        Ok(text) if !binary && !text.contains('\0') => text,
+        _ => return Ok(Dump::from_bytes(&bytes)),
+    };
+    Dump::parse_hex(text).map_err(|e| format!("{path}: {e}"))
+}
+
+fn label(reg: &Register) -> String {
+    match reg.channel {
+        Some(n) => format!("CH{n}.{}", reg.info.name),
+        None => reg.info.name.to_string(),
+    }
+}
+
+fn field_value(reg: &Register, value: u32, index: usize) -> String {
+    let field = &reg.info.fields[index];
+    let v = field.read(value);
+    match field.value_name(v) {
+        Some(name) => format!("{v} ({name})"),
+        None if field.mask > 0xFF => format!("{v:#x}"),
+        None => format!("{v}"),
+    }
+}
+
+fn print_dump(dump: &Dump) {
+    for reg in dump.registers() {
+        let Some(value) = reg.value else {
+            println!("0x{:03x} {:<22} --------", reg.offset, label(&reg));
+            continue;
+        };
+        println!("0x{:03x} {:<22} 0x{value:08x}", reg.offset, label(&reg));
+        for (i, field) in reg.info.fields.iter().enumerate() {
+            println!("      {:<22} {}", field.name, field_value(&reg, value, i));
+        }
+    }
+
+    // Addresses split over two registers
+    for n in 0..CHANNELS {
+        let base = channel_offset(n);
+        let addr = |up: usize, lw: usize| {
+            Some(((dump.word(base + up)? as u64) << 32) | dump.word(base + lw)? as u64)
+        };
+        if let (Some(ddr), Some(crt)) = (addr(0x00, 0x04), addr(0x10, 0x14)) {
+            println!(
+                "CH{n}: DDR 0x{ddr:016x} CRT 0x{crt:016x} moved {:#x} bytes",
+                crt.saturating_sub(ddr)
+            );
+        }
+    }
+}
+
+fn print_diff(old: &Dump, new: &Dump) -> usize {
+    let mut changed = 0;
+    for (a, b) in old.diff(new) {
+        changed += 1;
+        let show = |v: Option<u32>| v.map_or("--------".to_string(), |v| format!("0x{v:08x}"));
+        println!(
+            "0x{:03x} {:<22} {} -> {}",
+            a.offset,
+            label(&a),
+            show(a.value),
+            show(b.value)
+        );
+        let (Some(va), Some(vb)) = (a.value, b.value) else {
+            continue;
+        };
+        for (i, field) in a.info.fields.iter().enumerate() {
+            if field.read(va) != field.read(vb) {
+                println!(
+                    "      {:<22} {} -> {}",
+                    field.name,
+                    field_value(&a, va, i),
+                    field_value(&b, vb, i)
+                );
+            }
+        }
+    }
+    changed
+}
+
+fn main() -> ExitCode {
+    let mut binary = false;
+    let mut diff = false;
+    let mut paths = Vec::new();
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--binary" => binary = true,
+            "--diff" => diff = true,
+            "-h" | "--help" => return usage(),
+            _ => paths.push(arg),
+        }
+    }
+
+    let result = match (diff, paths.as_slice()) {
+        (false, [path]) => load(path, binary).map(|dump| print_dump(&dump)),
+        (true, [old, new]) => load(old, binary).and_then(|old| {
+            let new = load(new, binary)?;
+            if print_diff(&old, &new) == 0 {
+                println!("no differences");
+            }
+            Ok(())
+        }),
+        _ => return usage(),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("ddma-decode: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,262 @@
+//! Transfers of arbitrary byte slices
+//!
+//! The controller needs DDR addresses aligned to 4 bytes and sizes that are a
+//! multiple of 4 (following C reference). Slices go through the channel buffer
+//! as a bounce buffer, and the tail beyond the last full word is either padded
+//! or left to the caller to move by PIO. Slices in DMA-capable memory meeting
+//! these rules can be transferred in place with the `unsafe` variants.
+
+use core::{hint::spin_loop, ops::Range, time::Duration};
+
+use log::trace;
+
+use crate::{
+    Channel, DdmaError,
+    time::{Clock, Deadline},
+};
+
+/// Handling of the bytes after the last full 4-byte word of a slice
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TailPolicy {
+    /// Round the transfer up to whole words: zero padding is sent after the
+    /// data, received padding bytes are discarded
+    Pad,
+    /// Transfer whole words only and leave the tail to the caller, see
+    /// [`SliceTransfer::pio_tail`]
+    Pio,
+}
+
+/// A transfer of a byte slice started on a channel
+///
+/// Dropping it before completion stops the channel. The memory region and
+/// cyclic mode of the channel are restored afterwards, so the channel buffer
+/// can be used again.
+pub struct SliceTransfer<'a> {
+    channel: &'a mut Channel,
+    /// Destination of a read through the bounce buffer
+    rx: Option<&'a mut [u8]>,
+    /// Bytes of the slice handled by the transfer
+    len: usize,
+    /// Bytes moved by the controller
+    dma_len: usize,
+    /// Memory region and cyclic mode of the channel before the transfer
+    saved: (u64, usize, bool),
+    done: bool,
+}
+
+fn round_down(len: usize) -> usize {
+    len & !3
+}
+
+fn round_up(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+impl Channel {
+    /// Start writing `data` to the device
+    ///
+    /// The slice is copied to the channel buffer, and only as much as the
+    /// buffer holds is written (see [`SliceTransfer::len`]).
+    pub fn write_slice<'a>(
+        &'a mut self,
+        data: &'a [u8],
+        tail: TailPolicy,
+    ) -> Result<SliceTransfer<'a>, DdmaError> {
+        let (addr, len) = self.bounce(data.len())?;
+        let dma_len = dma_len(len, tail);
+        let buff = self.buff_mut();
+        for (i, &b) in data[..len].iter().enumerate() {
+            buff.set(i, b);
+        }
+        for i in len..dma_len {
+            buff.set(i, 0);
+        }
+        SliceTransfer::start(self, None, addr, len, tail)
+    }
+
+    /// Start reading from the device into `data`
+    ///
+    /// The slice is filled through the channel buffer, when the transfer
+    /// completes, up to the size of the buffer as for [`Channel::write_slice`].
+    pub fn read_slice<'a>(
+        &'a mut self,
+        data: &'a mut [u8],
+        tail: TailPolicy,
+    ) -> Result<SliceTransfer<'a>, DdmaError> {
+        let (addr, len) = self.bounce(data.len())?;
+        SliceTransfer::start(self, Some(&mut data[..len]), addr, len, tail)
+    }
+
+    /// Start writing `data` to the device in place if it is aligned, through
+    /// the channel buffer otherwise
+    ///
+    /// # Safety
+    ///
+    /// `bus_addr` must be the bus address of `data`, in memory the controller
+    /// can read and coherent with it (or cleaned by the caller). The returned
+    /// transfer must be dropped or completed, not leaked with `mem::forget`:
+    /// the controller keeps reading the memory until it is stopped, past the
+    /// end of the borrow of `data`.
+    pub unsafe fn write_slice_in_place<'a>(
+        &'a mut self,
+        data: &'a [u8],
+        bus_addr: u64,
+        tail: TailPolicy,
+    ) -> Result<SliceTransfer<'a>, DdmaError> {
+        if zero_copy(bus_addr, data.len(), tail) {
+            return SliceTransfer::start(self, None, bus_addr, data.len(), tail);
+        }
+        self.write_slice(data, tail)
+    }
+
+    /// Start reading from the device into `data` in place if it is aligned,
+    /// through the channel buffer otherwise
+    ///
+    /// # Safety
+    ///
+    /// `bus_addr` must be the bus address of `data`, in memory the controller
+    /// can write and coherent with it (or invalidated by the caller). The
+    /// returned transfer must be dropped or completed, not leaked with
+    /// `mem::forget`: the controller keeps writing the memory until it is
+    /// stopped, past the end of the borrow of `data`.
+    pub unsafe fn read_slice_in_place<'a>(
+        &'a mut self,
+        data: &'a mut [u8],
+        bus_addr: u64,
+        tail: TailPolicy,
+    ) -> Result<SliceTransfer<'a>, DdmaError> {
+        if zero_copy(bus_addr, data.len(), tail) {
+            let len = data.len();
+            return SliceTransfer::start(self, None, bus_addr, len, tail);
+        }
+        self.read_slice(data, tail)
+    }
+
+    /// Bus address of the channel buffer and the part of a `len`-byte slice
+    /// it holds
+    ///
+    /// Fails with `DdmaError::Busy` while the channel runs, before the caller
+    /// touches the buffer the controller may still be using.
+    fn bounce(&self, len: usize) -> Result<(u64, usize), DdmaError> {
+        if self.is_running() {
+            return Err(DdmaError::Busy);
+        }
+        let buff = self.try_buff().ok_or(DdmaError::NoBuffer)?;
+        let len = len.min(round_down(self.capacity()));
+        trace!("Channel {} bounces {} bytes", self.index(), len);
+        Ok((buff.bus_addr(), len))
+    }
+}
+
+/// Check if a slice can be transferred in place
+fn zero_copy(addr: u64, len: usize, tail: TailPolicy) -> bool {
+    addr.is_multiple_of(4) && (len.is_multiple_of(4) || tail == TailPolicy::Pio)
+}
+
+fn dma_len(len: usize, tail: TailPolicy) -> usize {
+    match tail {
+        TailPolicy::Pad => round_up(len),
+        TailPolicy::Pio => round_down(len),
+    }
+}
+
+impl<'a> SliceTransfer<'a> {
+    fn start(
+        channel: &'a mut Channel,
+        rx: Option<&'a mut [u8]>,
+        addr: u64,
+        len: usize,
+        tail: TailPolicy,
+    ) -> Result<Self, DdmaError> {
+        if channel.is_running() {
+            return Err(DdmaError::Busy);
+        }
+
+        let dma_len = dma_len(len, tail);
+        let saved = channel.set_region(addr, dma_len);
+        // Nothing for the controller if the slice is shorter than a word
+        if dma_len > 0 {
+            channel.active();
+        }
+        Ok(Self {
+            channel,
+            rx,
+            len,
+            dma_len,
+            saved,
+            done: dma_len == 0,
+        })
+    }
+
+    /// Bytes of the slice handled by the transfer, including the PIO tail
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check if the transfer handles no byte of the slice
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Range of the slice the controller does not move, left to the caller
+    /// with [`TailPolicy::Pio`]
+    pub fn pio_tail(&self) -> Range<usize> {
+        self.dma_len.min(self.len)..self.len
+    }
+
+    /// Check whether the controller finished, consuming the completion
+    pub fn poll(&mut self) -> bool {
+        if self.done {
+            return true;
+        }
+        if !self.channel.poll_complete() {
+            return false;
+        }
+
+        if let Some(rx) = self.rx.as_deref_mut() {
+            let n = self.dma_len.min(rx.len());
+            let buff = self.channel.buff();
+            for (i, b) in rx[..n].iter_mut().enumerate() {
+                *b = buff.get(i).unwrap_or_default();
+            }
+        }
+        self.done = true;
+        true
+    }
+
+    /// Wait for the controller to finish, measured with the generic timer
+    #[cfg(target_arch = "aarch64")]
+    pub fn wait_complete(&mut self, timeout: Duration) -> Result<(), DdmaError> {
+        self.wait_complete_with(&crate::time::GenericTimer, timeout)
+    }
+
+    /// Wait for the controller to finish, measured with `clock`
+    pub fn wait_complete_with(
+        &mut self,
+        clock: &impl Clock,
+        timeout: Duration,
+    ) -> Result<(), DdmaError> {
+        let deadline = Deadline::new(clock, timeout);
+        while !self.poll() {
+            if deadline.expired() {
+                trace!(
+                    "Channel {} slice transfer timed out after {:?}",
+                    self.channel.index(),
+                    timeout
+                );
+                return Err(DdmaError::Timeout);
+            }
+            spin_loop();
+        }
+        Ok(())
+    }
+}
+
+impl Drop for SliceTransfer<'_> {
+    fn drop(&mut self) {
+        if !self.done {
+            self.channel.deactive();
+        }
+        self.channel.restore_region(self.saved);
+    }
+}
@@ -1,120 +1,622 @@
-use core::{hint::spin_loop, ptr::NonNull};
+use alloc::sync::Arc;
+use core::{hint::spin_loop, ptr::NonNull, time::Duration};
 
 use dma_api::DVec;
 use log::trace;
 use tock_registers::interfaces::*;
 
-use crate::reg::*;
+use crate::{
+    DdmaError, DmaChannelConfig, DmaTransfer, Shared,
+    reg::*,
+    soc::DmaRequest,
+    time::{Clock, Deadline, spin_wait},
+};
 
 pub struct Channel {
     n: u8,
     reg: NonNull<DmaChannelRegisters>,
-    buff: DVec<u8>,
+    ctrl: NonNull<DdmaRegister>,
+    /// Driver-owned buffer, `None` for channels prepared from a `DmaTransfer`
+    buff: Option<DVec<u8>>,
+    /// Size of the memory region the channel transfers from or to
+    len: usize,
+    /// Bus address of the memory region
+    mem_addr: u64,
+    /// Size of the current logical transfer
+    xfer_len: usize,
+    /// Offset of the hardware block being transferred, when the logical
+    /// transfer is split into blocks of at most `max_block` bytes
+    block: usize,
+    /// Largest hardware block, `MAX_BLOCK_SIZE` unless lowered
+    max_block: usize,
+    shared: Arc<Shared>,
+    /// Restart the block automatically when it completes
+    cyclic: bool,
+    /// A transfer was submitted and its completion not yet consumed
+    submitted: bool,
+    retry: RetryPolicy,
+    /// Restarts of the current block so far
+    retries: u32,
 }
 
 unsafe impl Send for Channel {}
 
+/// How often a channel is recovered and restarted after a failed attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Restarts of a block before giving up on it
+    pub max_retries: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { max_retries: 3 }
+    }
+}
+
+/// State of the channel FIFO, from the channel status register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FifoStatus {
+    pub full: bool,
+    pub empty: bool,
+}
+
+/// Configuration of a channel with its own buffer
+///
+/// The request line part comes from a [`DmaRequest`], see
+/// [`ChannelConfig::for_request`]. Channels on a caller-provided memory region
+/// are configured with `DmaChannelConfig` instead.
 #[derive(Debug, Clone)]
 pub struct ChannelConfig {
-    pub slave_id: u8,
-    pub direction: crate::DmaDirection,
+    pub(crate) slave_id: u8,
+    pub(crate) direction: crate::DmaDirection,
     pub timeout_count: u32,
     pub blk_size: usize,
-    pub dev_addr: u32,
+    pub(crate) dev_addr: u32,
     pub irq: bool,
 }
 
+impl ChannelConfig {
+    /// Configuration for a peripheral request line, taking the slave ID,
+    /// direction and FIFO address from the SoC table or a `RawRequest`
+    pub fn for_request(request: impl DmaRequest, blk_size: usize, irq: bool) -> Self {
+        Self {
+            slave_id: request.slave_id(),
+            direction: request.direction(),
+            timeout_count: 0,
+            blk_size,
+            dev_addr: request.fifo_addr(),
+            irq,
+        }
+    }
+
+    /// Request signal source number of the peripheral
+    pub fn slave_id(&self) -> u8 {
+        self.slave_id
+    }
+
+    /// Transfer direction
+    pub fn direction(&self) -> crate::DmaDirection {
+        self.direction
+    }
+
+    /// Bus address of the peripheral FIFO
+    pub fn dev_addr(&self) -> u32 {
+        self.dev_addr
+    }
+
+    /// Binding part of the configuration for channel `channel`
+    ///
+    /// The hardware timeout stays disabled, as for channels created with
+    /// `DDMA::new_channel`.
+    pub fn to_channel_config(&self, channel: u8) -> DmaChannelConfig {
+        DmaChannelConfig {
+            channel,
+            peripheral_id: self.slave_id,
+            direction: self.direction,
+            timeout_enable: false,
+            timeout_count: self.timeout_count,
+            irq: self.irq,
+        }
+    }
+}
+
 impl Channel {
     pub(crate) fn new(
-        n: u8,
         reg: NonNull<DmaChannelRegisters>,
-        config: ChannelConfig,
-    ) -> Option<Self> {
-        let mut s = Self {
-            n,
-            reg,
-            buff: DVec::zeros(config.blk_size, 128, dma_api::Direction::Bidirectional)?,
-        };
-        let ddr = s.buff.bus_addr();
+        ctrl: NonNull<DdmaRegister>,
+        config: &DmaChannelConfig,
+        transfer: &DmaTransfer,
+        buff: Option<DVec<u8>>,
+        shared: Arc<Shared>,
+    ) -> Result<Self, DdmaError> {
+        transfer.validate()?;
+        let dev_addr = shared.dev_addr(transfer.dev_addr)?;
 
-        // Check DDR address alignment (following C reference)
-        if !ddr.is_multiple_of(4) {
-            trace!("DDR addr 0x{:x} must be aligned with 4 bytes.", ddr);
-            return None;
+        // Stop a channel left enabled before reprogramming it, without
+        // building the channel: dropping it on error would take the lock
+        // held by the caller
+        let chan_reg = unsafe { reg.as_ref() };
+        if chan_reg.ctl.is_set(DMA_CHALX_CTL::CHALX_EN) {
+            reset(chan_reg, config.channel, shared.wait_polls())?;
         }
 
-        // Check transfer size alignment (following C reference)
-        if config.blk_size < 4 || !config.blk_size.is_multiple_of(4) {
-            trace!(
-                "Invalid transfer size {} bytes, it should be an integer multiple of 4 bytes.",
-                config.blk_size
-            );
-            return None;
-        }
-
-        if s.reg().ctl.is_set(DMA_CHALX_CTL::CHALX_EN) {
-            s.reset();
-        }
+        let mut s = Self {
+            n: config.channel,
+            reg,
+            ctrl,
+            buff,
+            len: transfer.size,
+            mem_addr: transfer.mem_addr,
+            xfer_len: transfer.size,
+            block: 0,
+            max_block: DmaChannelRegisters::MAX_BLOCK_SIZE,
+            shared,
+            cyclic: false,
+            submitted: false,
+            retry: RetryPolicy::default(),
+            retries: 0,
+        };
 
-        s.reg().ddr_lwaddr.set((ddr & 0xFFFF_FFFF) as u32);
-        s.reg().ddr_upaddr.set((ddr >> 32) as u32);
-        s.reg().dev_addr.set(config.dev_addr);
-        s.reg().ts.set(config.blk_size as u32);
+        s.write_transfer(transfer, dev_addr);
 
         s.reg().ctl.modify(match config.direction {
             crate::DmaDirection::MemoryToDevice => DMA_CHALX_CTL::CHALX_MODE::Tx,
             crate::DmaDirection::DeviceToMemory => DMA_CHALX_CTL::CHALX_MODE::Rx,
         });
+        s.reg().timeout_cnt.write(
+            DMA_CHALX_TIMEOUT_CNT::TIMEOUT_CNT.val(config.timeout_count)
+                + DMA_CHALX_TIMEOUT_CNT::TIMEOUT_EN.val(config.timeout_enable as u32),
+        );
 
-        Some(s)
+        Ok(s)
     }
 
-    pub fn index(&self) -> u8 {
-        self.n
+    /// Take over a channel bound outside of this driver, keeping its
+    /// programming as found in the hardware
+    ///
+    /// No register is written, so a running transfer goes on undisturbed. The
+    /// channel has no driver-owned buffer and its current block is taken as
+    /// the whole transfer.
+    pub(crate) fn adopt(
+        reg: NonNull<DmaChannelRegisters>,
+        ctrl: NonNull<DdmaRegister>,
+        n: u8,
+        shared: Arc<Shared>,
+    ) -> Self {
+        let chan_reg = unsafe { reg.as_ref() };
+        let mem_addr =
+            ((chan_reg.ddr_upaddr.get() as u64) << 32) | chan_reg.ddr_lwaddr.get() as u64;
+        let size = chan_reg.ts.get() as usize;
+        let running = chan_reg.ctl.is_set(DMA_CHALX_CTL::CHALX_EN);
+        // The running transfer is in flight for the driver from now on, its
+        // completion is not spurious
+        #[cfg(feature = "stats")]
+        if running {
+            shared.stats.submitted(n, size);
+        }
+
+        Self {
+            n,
+            reg,
+            ctrl,
+            buff: None,
+            len: size,
+            mem_addr,
+            xfer_len: size,
+            block: 0,
+            max_block: DmaChannelRegisters::MAX_BLOCK_SIZE,
+            shared,
+            cyclic: false,
+            submitted: running,
+            retry: RetryPolicy::default(),
+            retries: 0,
+        }
     }
 
-    fn reset(&mut self) {
-        // Disable channel first (following C reference)
-        self.reg().ctl.modify(DMA_CHALX_CTL::CHALX_EN::CLEAR);
-        while self.reg().ctl.is_set(DMA_CHALX_CTL::CHALX_EN) {
-            spin_loop();
+    /// Program the memory address, device address and size of the next block
+    ///
+    /// Both addresses go through the `AddressTranslation` of the controller.
+    /// The channel must not be running.
+    pub fn set_transfer(&mut self, transfer: &DmaTransfer) -> Result<(), DdmaError> {
+        if self.is_running() {
+            return Err(DdmaError::Busy);
         }
+        transfer.validate()?;
+        let dev_addr = self.shared.dev_addr(transfer.dev_addr)?;
+        self.write_transfer(transfer, dev_addr);
+        Ok(())
+    }
 
-        // Perform soft reset (following C reference)
-        self.reg().ctl.modify(DMA_CHALX_CTL::CHALX_SRST::SET);
-        self.reg().ctl.modify(DMA_CHALX_CTL::CHALX_SRST::CLEAR);
+    fn write_transfer(&mut self, transfer: &DmaTransfer, dev_addr: u32) {
+        self.reg().dev_addr.set(dev_addr);
+        self.mem_addr = transfer.mem_addr;
+        self.len = transfer.size;
+        self.xfer_len = transfer.size;
+        self.retries = 0;
+        self.program_block(0);
+    }
 
-        trace!("Channel {} reset done", self.n);
+    /// Point the next single transfer at `len` bytes at `mem_addr`, keeping
+    /// the buffer and capacity of the channel
+    ///
+    /// Returns the previous memory region and cyclic mode, to be restored
+    /// with [`Channel::restore_region`].
+    pub(crate) fn set_region(&mut self, mem_addr: u64, len: usize) -> (u64, usize, bool) {
+        let prev = (self.mem_addr, self.xfer_len, self.cyclic);
+        self.mem_addr = mem_addr;
+        self.xfer_len = len;
+        self.cyclic = false;
+        self.retries = 0;
+        self.program_block(0);
+        prev
+    }
+
+    /// Restore the memory region and cyclic mode saved by `set_region`
+    pub(crate) fn restore_region(&mut self, saved: (u64, usize, bool)) {
+        let (mem_addr, len, cyclic) = saved;
+        self.set_region(mem_addr, len);
+        self.cyclic = cyclic;
+    }
+
+    /// Program the hardware block starting at `offset` of the logical transfer
+    fn program_block(&mut self, offset: usize) {
+        let ddr = self.shared.mem_addr(self.mem_addr + offset as u64);
+        let size = (self.xfer_len - offset).min(self.max_block);
+        self.reg().ddr_lwaddr.set((ddr & 0xFFFF_FFFF) as u32);
+        self.reg().ddr_upaddr.set((ddr >> 32) as u32);
+        self.reg().ts.set(size as u32);
+        self.block = offset;
+    }
+
+    pub fn index(&self) -> u8 {
+        self.n
     }
 
     pub fn active(&mut self) {
+        self.start();
+        #[cfg(feature = "stats")]
+        self.shared.stats.submitted(self.n, self.xfer_len);
+        trace!("Channel {} activated", self.n);
+    }
+
+    /// Start the programmed block, without counting a new transfer
+    fn start(&mut self) {
         // Clear any pending interrupts first (following C reference)
-        // Note: This would need to be done at the controller level
-        // but we can clear channel-specific status here
+        self.shared.discard_completed(self.n);
+        self.ack_complete();
         self.reg().ctl.modify(DMA_CHALX_CTL::CHALX_EN::SET);
-        trace!("Channel {} activated", self.n);
+        self.submitted = true;
     }
 
-    pub fn clear_and_active(&mut self, dma: &mut crate::DDMA) {
+    pub fn clear_and_active(&mut self, dma: &crate::DDMA) {
         // Clear pending interrupts at controller level (following C reference)
         dma.clear_transfer_complete(self.n);
         self.active();
     }
 
     pub fn deactive(&mut self) {
+        self.stop();
+        #[cfg(feature = "stats")]
+        self.shared.stats.deactivated(self.n);
+    }
+
+    /// Stop the channel, without counting an aborted transfer
+    fn stop(&mut self) {
         self.reg().ctl.modify(DMA_CHALX_CTL::CHALX_EN::CLEAR);
+        self.submitted = false;
+    }
+
+    /// Size of the memory region of the channel in bytes
+    pub fn capacity(&self) -> usize {
+        self.len
+    }
+
+    /// Limit the hardware blocks of the next transfers to `size` bytes, for
+    /// peripherals that need a completion every `size` bytes
+    ///
+    /// `size` must be a multiple of 4 bytes, at most
+    /// `DmaChannelRegisters::MAX_BLOCK_SIZE`.
+    pub fn set_max_block_size(&mut self, size: usize) -> Result<(), DdmaError> {
+        if self.is_running() {
+            return Err(DdmaError::Busy);
+        }
+        if size < 4 || !size.is_multiple_of(4) || size > DmaChannelRegisters::MAX_BLOCK_SIZE {
+            trace!("Invalid block size {} bytes for channel {}", size, self.n);
+            return Err(DdmaError::InvalidSize);
+        }
+        self.max_block = size;
+        Ok(())
+    }
+
+    /// Program the size of the next transfer from the channel buffer
+    ///
+    /// Transfers larger than the block size limit are split into hardware
+    /// blocks, see [`Channel::poll_complete`]. A cyclic transfer restarts from
+    /// the beginning of the buffer each time the block completes, until the
+    /// channel is deactivated.
+    pub fn prepare(&mut self, len: usize, cyclic: bool) -> Result<(), DdmaError> {
+        if self.is_running() {
+            return Err(DdmaError::Busy);
+        }
+
+        // Check transfer size alignment (following C reference)
+        if len < 4 || !len.is_multiple_of(4) || len > self.capacity() {
+            trace!(
+                "Invalid transfer size {} bytes for channel {} (buffer {} bytes)",
+                len,
+                self.n,
+                self.capacity()
+            );
+            return Err(DdmaError::InvalidSize);
+        }
+
+        self.xfer_len = len;
+        self.retries = 0;
+        self.program_block(0);
+        self.cyclic = cyclic;
+        Ok(())
+    }
+
+    /// Check whether the submitted block has completed, consuming the completion
+    ///
+    /// Completions are taken from `DMA_STAT` when polling, or from the ones
+    /// latched by `IrqHandler::handle_irq`. The channel is disabled once its
+    /// block completed, a cyclic channel is restarted right away.
+    ///
+    /// A split transfer raises one completion per hardware block. Intermediate
+    /// blocks are consumed here by starting the next block and only the last
+    /// one is reported, so in interrupt mode this must be called after each
+    /// interrupt of the channel.
+    pub fn poll_complete(&mut self) -> bool {
+        let latched = self.shared.take_completed(self.n);
+        let pending = self.ack_complete();
+        if !(latched || pending) {
+            return false;
+        }
+
+        // Stop the finished block so the channel can be reprogrammed
+        self.stop();
+        self.retries = 0;
+
+        let next = self.block + (self.xfer_len - self.block).min(self.max_block);
+        if next < self.xfer_len {
+            trace!(
+                "Channel {} block done, next block at offset {:#x}",
+                self.n, next
+            );
+            self.program_block(next);
+            self.start();
+            return false;
+        }
+
+        #[cfg(feature = "stats")]
+        self.shared.stats.completed(self.n);
+        if self.cyclic {
+            self.program_block(0);
+            self.active();
+        }
+        true
+    }
+
+    /// Clear the completion of this channel pending in DMA_STAT, if any
+    fn ack_complete(&self) -> bool {
+        let pending = self.ctrl().is_channel_complete(self.n as usize);
+        if pending {
+            self.ctrl().clear_channel_complete(self.n as usize);
+        }
+        pending
+    }
+
+    /// Wait for the submitted block to complete, measured with the generic timer
+    #[cfg(target_arch = "aarch64")]
+    pub fn wait_complete(&mut self, timeout: Duration) -> Result<(), DdmaError> {
+        self.wait_complete_with(&crate::time::GenericTimer, timeout)
+    }
+
+    /// Wait for the submitted block to complete, measured with `clock`
+    ///
+    /// The completion is consumed as with [`Channel::poll_complete`].
+    pub fn wait_complete_with(
+        &mut self,
+        clock: &impl Clock,
+        timeout: Duration,
+    ) -> Result<(), DdmaError> {
+        let deadline = Deadline::new(clock, timeout);
+        loop {
+            if self.poll_complete() {
+                return Ok(());
+            }
+            if deadline.expired() {
+                trace!("Channel {} timed out after {:?}", self.n, timeout);
+                #[cfg(feature = "stats")]
+                self.shared.stats.timed_out(self.n);
+                return Err(DdmaError::Timeout);
+            }
+            spin_loop();
+        }
+    }
+
+    /// Number of bytes moved so far by the current transfer
+    ///
+    /// Computed from the current address registers, which the controller
+    /// advances from the programmed DDR address as data is transferred, plus
+    /// the blocks of a split transfer already done. The upper address half is
+    /// re-read while the lower half carries into it, at most the wait budget
+    /// of the controller, then `DdmaError::Timeout` is returned.
+    pub fn transferred(&self) -> Result<usize, DdmaError> {
+        let reg = self.reg();
+        let start = ((reg.ddr_upaddr.get() as u64) << 32) | reg.ddr_lwaddr.get() as u64;
+        let current = spin_wait(self.wait_budget(), || {
+            let up = reg.crt_upaddr.get();
+            let lw = reg.crt_lwaddr.get();
+            // Re-read if the lower half carried into the upper half meanwhile
+            (reg.crt_upaddr.get() == up).then_some(((up as u64) << 32) | lw as u64)
+        })
+        .ok_or(DdmaError::Timeout)?;
+        Ok((self.block + current.saturating_sub(start) as usize).min(self.xfer_len))
+    }
+
+    /// Check if the controller moved the whole current block, whether or not
+    /// it raised the completion
+    pub(crate) fn block_moved(&self) -> Result<bool, DdmaError> {
+        Ok(self.transferred()? >= self.block + self.reg().ts.get() as usize)
+    }
+
+    /// State of the channel FIFO
+    pub fn fifo_status(&self) -> FifoStatus {
+        let sts = self.reg().sts.extract();
+        FifoStatus {
+            full: sts.is_set(DMA_CHALX_STS::FIFO_FULL),
+            empty: sts.is_set(DMA_CHALX_STS::FIFO_EMPTY),
+        }
+    }
+
+    /// Direction the channel is programmed for
+    pub fn direction(&self) -> crate::DmaDirection {
+        match self.reg().ctl.read_as_enum(DMA_CHALX_CTL::CHALX_MODE) {
+            Some(DMA_CHALX_CTL::CHALX_MODE::Value::Rx) => crate::DmaDirection::DeviceToMemory,
+            _ => crate::DmaDirection::MemoryToDevice,
+        }
+    }
+
+    /// Stop the channel, soft-reset it and program the current block again
+    ///
+    /// Used to get a stalled channel out of a wedged state. The block in
+    /// flight is aborted and programmed from its start, then restarted if
+    /// `restart` is set: a restarted TX block sends its data again. Fails with
+    /// `Timeout` if the channel does not stop within the wait budget.
+    pub fn recover(&mut self, restart: bool) -> Result<(), DdmaError> {
+        // The soft reset may clear the channel registers (following C
+        // reference, which programs the channel after resetting it)
+        let dev_addr = self.reg().dev_addr.get();
+        let mode = self.reg().ctl.read(DMA_CHALX_CTL::CHALX_MODE);
+        let timeout_cnt = self.reg().timeout_cnt.get();
+
+        self.stop();
+        reset(self.reg(), self.n, self.shared.wait_polls())?;
+        self.shared.discard_completed(self.n);
+        self.ack_complete();
+
+        self.reg().dev_addr.set(dev_addr);
+        self.reg().timeout_cnt.set(timeout_cnt);
+        self.reg().ctl.write(DMA_CHALX_CTL::CHALX_MODE.val(mode));
+        self.program_block(self.block);
+        trace!("Channel {} recovered at offset {:#x}", self.n, self.block);
+
+        // A restarted block carries on the same transfer
+        if restart {
+            self.start();
+        } else {
+            #[cfg(feature = "stats")]
+            self.shared.stats.deactivated(self.n);
+        }
+        Ok(())
+    }
+
+    /// Set how often [`Channel::retry`] restarts a block
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.retry = policy;
+    }
+
+    /// Retry policy of the channel, `RetryPolicy::default()` unless set
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry
+    }
+
+    /// Number of restarts of the current block so far
+    pub fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    /// Recover the channel after a failed attempt and restart the current
+    /// block, as allowed by the retry policy
+    ///
+    /// Only this channel is soft-reset and reprogrammed, the other channels
+    /// keep running. Returns `false` once the policy is exhausted, the channel
+    /// is then stopped. Fails with `Timeout` if the channel does not stop.
+    pub fn retry(&mut self) -> Result<bool, DdmaError> {
+        if self.retries >= self.retry.max_retries {
+            trace!("Channel {} gives up after {} retries", self.n, self.retries);
+            self.recover(false)?;
+            return Ok(false);
+        }
+        self.retries += 1;
+        trace!("Channel {} retry {}", self.n, self.retries);
+        self.recover(true)?;
+        Ok(true)
+    }
+
+    /// Start the prepared transfer and wait for it, measured with the generic
+    /// timer, see [`Channel::transfer_with`]
+    #[cfg(target_arch = "aarch64")]
+    pub fn transfer(&mut self, timeout: Duration) -> Result<(), DdmaError> {
+        self.transfer_with(&crate::time::GenericTimer, timeout)
+    }
+
+    /// Start the prepared transfer and wait for it, measured with `clock`
+    ///
+    /// Each attempt is given `timeout`. A block that times out is restarted
+    /// with [`Channel::retry`] until the retry policy is exhausted, and the
+    /// transfer then fails with `Timeout`.
+    pub fn transfer_with(
+        &mut self,
+        clock: &impl Clock,
+        timeout: Duration,
+    ) -> Result<(), DdmaError> {
+        self.retries = 0;
+        self.active();
+        loop {
+            match self.wait_complete_with(clock, timeout) {
+                Err(DdmaError::Timeout) => {
+                    if !self.retry()? {
+                        return Err(DdmaError::Timeout);
+                    }
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Check if a submitted transfer has not completed yet
+    pub fn is_submitted(&self) -> bool {
+        self.submitted
     }
 
     fn reg(&self) -> &DmaChannelRegisters {
         unsafe { self.reg.as_ref() }
     }
 
+    fn ctrl(&self) -> &DdmaRegister {
+        unsafe { self.ctrl.as_ref() }
+    }
+
+    /// Driver-owned buffer of a channel created with `DDMA::new_channel`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the channel was prepared from a `DmaTransfer`.
     pub fn buff(&self) -> &DVec<u8> {
-        &self.buff
+        self.buff
+            .as_ref()
+            .expect("channel prepared from a DmaTransfer has no buffer")
     }
 
+    /// Mutable access to the driver-owned buffer, see [`Channel::buff`]
     pub fn buff_mut(&mut self) -> &mut DVec<u8> {
-        &mut self.buff
+        self.buff
+            .as_mut()
+            .expect("channel prepared from a DmaTransfer has no buffer")
+    }
+
+    /// Driver-owned buffer, if any
+    pub fn try_buff(&self) -> Option<&DVec<u8>> {
+        self.buff.as_ref()
+    }
+
+    /// Mutable driver-owned buffer, if any
+    pub fn try_buff_mut(&mut self) -> Option<&mut DVec<u8>> {
+        self.buff.as_mut()
     }
 
     /// Debug channel registers
@@ -138,7 +640,27 @@ impl Channel {
             "  Channel Enabled: {}",
             reg.ctl.is_set(DMA_CHALX_CTL::CHALX_EN)
         );
-        trace!("  Buffer bus addr: 0x{:016x}", self.buff.bus_addr());
+        if let Some(buff) = &self.buff {
+            trace!("  Buffer bus addr: 0x{:016x}", buff.bus_addr());
+        }
+    }
+
+    /// Get the statistics counters of this channel
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> crate::ChannelStats {
+        self.shared.stats.snapshot(self.n)
+    }
+
+    /// Reset the statistics counters of this channel
+    #[cfg(feature = "stats")]
+    pub fn reset_stats(&self) {
+        self.shared.stats.reset(self.n);
+    }
+
+    /// Number of polls a busy-wait on this channel may take, see
+    /// `DDMA::set_wait_budget`
+    pub fn wait_budget(&self) -> u32 {
+        self.shared.wait_polls()
     }
 
     /// Check if channel is actually running
@@ -146,3 +668,44 @@ impl Channel {
         self.reg().ctl.is_set(DMA_CHALX_CTL::CHALX_EN)
     }
 }
+
+/// Disable and soft-reset channel `n`
+///
+/// Fails with `Timeout` if the channel does not clear CHALX_EN within `polls`
+/// reads.
+fn reset(reg: &DmaChannelRegisters, n: u8, polls: u32) -> Result<(), DdmaError> {
+    // Disable channel first (following C reference)
+    reg.ctl.modify(DMA_CHALX_CTL::CHALX_EN::CLEAR);
+    if spin_wait(polls, || {
+        (!reg.ctl.is_set(DMA_CHALX_CTL::CHALX_EN)).then_some(())
+    })
+    .is_none()
+    {
+        trace!("Channel {} did not stop after {} polls", n, polls);
+        return Err(DdmaError::Timeout);
+    }
+
+    // Perform soft reset (following C reference)
+    reg.ctl.modify(DMA_CHALX_CTL::CHALX_SRST::SET);
+    reg.ctl.modify(DMA_CHALX_CTL::CHALX_SRST::CLEAR);
+
+    trace!("Channel {} reset done", n);
+    Ok(())
+}
+
+impl Drop for Channel {
+    /// Stop the channel and release its binding, so the channel can be
+    /// requested again and the controller no longer accesses the buffer
+    fn drop(&mut self) {
+        self.deactive();
+        self.shared.discard_completed(self.n);
+
+        let _guard = self.shared.lock.lock();
+        let channel = self.n as usize;
+        self.ctrl().set_channel_interrupt_mask(channel, true);
+        self.ctrl().set_channel_config(channel, 0, false);
+        self.ctrl().set_channel_bind(channel, false);
+        self.shared.release(self.n);
+        trace!("Channel {} released", self.n);
+    }
+}
@@ -0,0 +1,169 @@
+//! Saving and restoring the register state of the controller across suspend
+
+use core::sync::atomic::Ordering;
+
+use log::trace;
+use tock_registers::{
+    LocalRegisterCopy,
+    interfaces::{ReadWriteable, Readable, Writeable},
+};
+
+use crate::{
+    DDMA,
+    reg::{DMA_CHALX_CTL, DMA_CTL, DdmaRegister},
+};
+
+/// Saved register state of one DDMA channel
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChannelContext {
+    /// DDR upper address register
+    pub ddr_upaddr: u32,
+    /// DDR lower address register
+    pub ddr_lwaddr: u32,
+    /// Device address register
+    pub dev_addr: u32,
+    /// Transfer size register
+    pub ts: u32,
+    /// Control register (enable and mode bits)
+    pub ctl: u32,
+    /// Timeout count register
+    pub timeout_cnt: u32,
+}
+
+/// Saved register state of a DDMA controller
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DdmaContext {
+    /// Global control register
+    pub dma_ctl: u32,
+    /// Request source selection for channels 0-3
+    pub chal_config: u32,
+    /// Request source selection for channels 4-7
+    pub chal_config1: u32,
+    /// Interrupt mask register
+    pub mask_int: u32,
+    /// Channel bind register
+    pub channel_bind: u32,
+    /// Channels whose completion was pending in DMA_STAT
+    pub pending: u8,
+    /// Upstream AXI write channel configuration
+    pub upaxi_awconfig: u32,
+    /// Upstream AXI read channel configuration
+    pub upaxi_arconfig: u32,
+    /// Downstream AXI write channel configuration
+    pub dwnaxi_awconfig: u32,
+    /// Downstream AXI read channel configuration
+    pub dwnaxi_arconfig: u32,
+    /// Per-channel register state
+    pub channels: [ChannelContext; DdmaRegister::MAX_CHANNELS],
+}
+
+impl DDMA {
+    /// Capture the register state of the controller and all channels
+    ///
+    /// Completions pending in DMA_STAT are saved as a bitmask of channels,
+    /// the status register itself cannot be written back.
+    pub fn save_context(&self) -> DdmaContext {
+        let reg = self.reg();
+        let mut ctx = DdmaContext {
+            dma_ctl: reg.dma_ctl.get(),
+            chal_config: reg.dma_chal_config.get(),
+            chal_config1: reg.dma_chal_config1.get(),
+            mask_int: reg.dma_mask_int.get(),
+            channel_bind: reg.dma_channel_bind.get(),
+            pending: reg.completion_mask(),
+            upaxi_awconfig: reg.dma_upaxi_awconfig.get(),
+            upaxi_arconfig: reg.dma_upaxi_arconfig.get(),
+            dwnaxi_awconfig: reg.dma_dwnaxi_awconfig.get(),
+            dwnaxi_arconfig: reg.dma_dwnaxi_arconfig.get(),
+            channels: Default::default(),
+        };
+
+        for (n, chan) in ctx.channels.iter_mut().enumerate() {
+            let creg = unsafe { self.channel_reg(n as u8).as_ref() };
+            *chan = ChannelContext {
+                ddr_upaddr: creg.ddr_upaddr.get(),
+                ddr_lwaddr: creg.ddr_lwaddr.get(),
+                dev_addr: creg.dev_addr.get(),
+                ts: creg.ts.get(),
+                ctl: creg.ctl.get(),
+                timeout_cnt: creg.timeout_cnt.get(),
+            };
+        }
+
+        trace!("DDMA context saved: {:?}", ctx);
+        ctx
+    }
+
+    /// Restore a context captured by [`DDMA::save_context`]
+    ///
+    /// The controller is stopped and all interrupts are masked while the
+    /// channels are reprogrammed. Channel registers are written while the
+    /// channel is disabled, request selection is restored before the bind
+    /// bits, and channels that were enabled are re-enabled before the
+    /// controller itself, following the C reference start sequence. A
+    /// channel that was enabled restarts its block from the saved DDR address,
+    /// unless the block had completed.
+    ///
+    /// Completions pending at save time are latched for their channels, as
+    /// the interrupt handler does, and `Channel::poll_complete` consumes them.
+    /// Channels unbound by the restore are no longer held by their handles,
+    /// and their latched completions are dropped.
+    pub fn restore_context(&self, ctx: &DdmaContext) {
+        let _guard = self.shared.lock.lock();
+        let reg = self.reg();
+
+        // Stop the controller and mask everything while reprogramming
+        reg.dma_ctl.write(DMA_CTL::DMA_ENABLE::CLEAR);
+        reg.dma_mask_int.set(u32::MAX);
+
+        reg.dma_upaxi_awconfig.set(ctx.upaxi_awconfig);
+        reg.dma_upaxi_arconfig.set(ctx.upaxi_arconfig);
+        reg.dma_dwnaxi_awconfig.set(ctx.dwnaxi_awconfig);
+        reg.dma_dwnaxi_arconfig.set(ctx.dwnaxi_arconfig);
+
+        // Channel registers may only be changed while the channel is disabled
+        for (n, chan) in ctx.channels.iter().enumerate() {
+            let creg = unsafe { self.channel_reg(n as u8).as_ref() };
+            let ctl = LocalRegisterCopy::<u32, DMA_CHALX_CTL::Register>::new(chan.ctl);
+            creg.ctl
+                .write(DMA_CHALX_CTL::CHALX_MODE.val(ctl.read(DMA_CHALX_CTL::CHALX_MODE)));
+            creg.ddr_upaddr.set(chan.ddr_upaddr);
+            creg.ddr_lwaddr.set(chan.ddr_lwaddr);
+            creg.dev_addr.set(chan.dev_addr);
+            creg.ts.set(chan.ts);
+            creg.timeout_cnt.set(chan.timeout_cnt);
+        }
+
+        // Request source selection first, then bind (following C reference sequence)
+        reg.dma_chal_config.set(ctx.chal_config);
+        reg.dma_chal_config1.set(ctx.chal_config1);
+        reg.dma_channel_bind.set(ctx.channel_bind);
+
+        // Drop completions raised while the block was powered down, and latch
+        // the ones pending at save time instead
+        reg.dma_stat.set(u32::MAX);
+        let bound = ctx.channel_bind as u8;
+        self.shared.owned.fetch_and(bound, Ordering::AcqRel);
+        self.shared.completed.fetch_and(bound, Ordering::AcqRel);
+        self.shared
+            .completed
+            .fetch_or(ctx.pending & bound, Ordering::AcqRel);
+        reg.dma_mask_int.set(ctx.mask_int);
+
+        // Activate channels, then start the controller (following C reference sequence)
+        // A finished block is not started again
+        for (n, chan) in ctx.channels.iter().enumerate() {
+            let ctl = LocalRegisterCopy::<u32, DMA_CHALX_CTL::Register>::new(chan.ctl);
+            if ctl.is_set(DMA_CHALX_CTL::CHALX_EN) && ctx.pending & (1 << n) == 0 {
+                let creg = unsafe { self.channel_reg(n as u8).as_ref() };
+                creg.ctl.modify(DMA_CHALX_CTL::CHALX_EN::SET);
+            }
+        }
+        let dma_ctl = LocalRegisterCopy::<u32, DMA_CTL::Register>::new(ctx.dma_ctl);
+        if dma_ctl.is_set(DMA_CTL::DMA_ENABLE) {
+            reg.dma_ctl.write(DMA_CTL::DMA_ENABLE::SET);
+        }
+
+        trace!("DDMA context restored");
+    }
+}
@@ -0,0 +1,363 @@
+//! Decoding of raw dumps of the `DdmaRegister` window, used by the
+//! `ddma-decode` host tool
+//!
+//! Field layouts come from the bitfield definitions of the driver, so the
+//! decoder always matches what the driver programs.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::reg::*;
+
+/// Size of the register window in bytes
+pub const WINDOW_SIZE: usize = 0x224;
+
+/// Number of channel blocks in the window
+pub const CHANNELS: usize = DdmaRegister::MAX_CHANNELS;
+
+/// Offset of the register block of channel `n`
+pub fn channel_offset(n: usize) -> usize {
+    DdmaRegister::CHANNEL_BASE_OFFSET + n * DdmaRegister::CHANNEL_REGISTER_SIZE
+}
+
+/// A bitfield of a register
+#[derive(Debug)]
+pub struct FieldInfo {
+    pub name: &'static str,
+    pub shift: usize,
+    pub mask: u32,
+    /// Names of enumerated values
+    pub values: &'static [(u32, &'static str)],
+}
+
+impl FieldInfo {
+    /// Extract the field from a register value
+    pub fn read(&self, value: u32) -> u32 {
+        (value >> self.shift) & self.mask
+    }
+
+    /// Name of an enumerated field value
+    pub fn value_name(&self, value: u32) -> Option<&'static str> {
+        self.values
+            .iter()
+            .find(|(v, _)| *v == value)
+            .map(|(_, name)| *name)
+    }
+}
+
+/// A register of the window
+#[derive(Debug)]
+pub struct RegisterInfo {
+    pub name: &'static str,
+    /// Offset from the window base, or from the channel block for channel
+    /// registers
+    pub offset: usize,
+    /// Bitfields, empty for plain 32-bit values
+    pub fields: &'static [FieldInfo],
+}
+
+macro_rules! field {
+    ($reg:ident :: $field:ident) => {
+        field!($reg::$field, [])
+    };
+    ($reg:ident :: $field:ident, [$(($value:expr, $vname:literal)),*]) => {
+        FieldInfo {
+            name: stringify!($field),
+            shift: $reg::$field.shift,
+            mask: $reg::$field.mask,
+            values: &[$(($value as u32, $vname)),*],
+        }
+    };
+}
+
+macro_rules! register {
+    ($name:literal, $offset:expr) => {
+        RegisterInfo {
+            name: $name,
+            offset: $offset,
+            fields: &[],
+        }
+    };
+    ($name:literal, $offset:expr, [$($field:expr),* $(,)?]) => {
+        RegisterInfo {
+            name: $name,
+            offset: $offset,
+            fields: &[$($field),*],
+        }
+    };
+}
+
+/// Global registers, reserved fields left out
+pub static GLOBAL_REGISTERS: &[RegisterInfo] = &[
+    register!(
+        "DMA_CTL",
+        0x00,
+        [field!(DMA_CTL::DMA_ENABLE), field!(DMA_CTL::DMA_SRST)]
+    ),
+    register!(
+        "DMA_CHAL_CONFIG",
+        0x04,
+        [
+            field!(DMA_CHAL_CONFIG::CHAL0_SEL),
+            field!(DMA_CHAL_CONFIG::CHAL0_SEL_EN),
+            field!(DMA_CHAL_CONFIG::CHAL1_SEL),
+            field!(DMA_CHAL_CONFIG::CHAL1_SEL_EN),
+            field!(DMA_CHAL_CONFIG::CHAL2_SEL),
+            field!(DMA_CHAL_CONFIG::CHAL2_SEL_EN),
+            field!(DMA_CHAL_CONFIG::CHAL3_SEL),
+            field!(DMA_CHAL_CONFIG::CHAL3_SEL_EN),
+        ]
+    ),
+    register!(
+        "DMA_STAT",
+        0x08,
+        [
+            field!(DMA_STAT::CHAL0_SEL),
+            field!(DMA_STAT::CHAL1_SEL),
+            field!(DMA_STAT::CHAL2_SEL),
+            field!(DMA_STAT::CHAL3_SEL),
+            field!(DMA_STAT::CHAL4_SEL),
+            field!(DMA_STAT::CHAL5_SEL),
+            field!(DMA_STAT::CHAL6_SEL),
+            field!(DMA_STAT::CHAL7_SEL),
+        ]
+    ),
+    register!(
+        "DMA_MASK_INT",
+        0x0C,
+        [
+            field!(DMA_MASK_INT::CHAL0_MASK),
+            field!(DMA_MASK_INT::CHAL1_MASK),
+            field!(DMA_MASK_INT::CHAL2_MASK),
+            field!(DMA_MASK_INT::CHAL3_MASK),
+            field!(DMA_MASK_INT::CHAL4_MASK),
+            field!(DMA_MASK_INT::CHAL5_MASK),
+            field!(DMA_MASK_INT::CHAL6_MASK),
+            field!(DMA_MASK_INT::CHAL7_MASK),
+            field!(DMA_MASK_INT::GLOBAL_EN),
+        ]
+    ),
+    register!("DMA_UPAXI_AWCONFIG", 0x10),
+    register!("DMA_UPAXI_ARCONFIG", 0x14),
+    register!("DMA_DWNAXI_AWCONFIG", 0x18),
+    register!("DMA_DWNAXI_ARCONFIG", 0x1C),
+    register!(
+        "DMA_CHANNEL_BIND",
+        0x20,
+        [field!(DMA_CHANNEL_BIND::DMA_CHANNEL_BIND)]
+    ),
+    register!("DMA_GCAP", 0x24),
+    register!(
+        "DMA_CHAL_CONFIG1",
+        0x28,
+        [
+            field!(DMA_CHAL_CONFIG1::CHAL4_SEL),
+            field!(DMA_CHAL_CONFIG1::CHAL4_SEL_EN),
+            field!(DMA_CHAL_CONFIG1::CHAL5_SEL),
+            field!(DMA_CHAL_CONFIG1::CHAL5_SEL_EN),
+            field!(DMA_CHAL_CONFIG1::CHAL6_SEL),
+            field!(DMA_CHAL_CONFIG1::CHAL6_SEL_EN),
+            field!(DMA_CHAL_CONFIG1::CHAL7_SEL),
+            field!(DMA_CHAL_CONFIG1::CHAL7_SEL_EN),
+        ]
+    ),
+];
+
+/// Registers of a channel block, at offsets from the block base
+pub static CHANNEL_REGISTERS: &[RegisterInfo] = &[
+    register!("DDR_UPADDR", 0x00),
+    register!("DDR_LWADDR", 0x04),
+    register!("DEV_ADDR", 0x08),
+    register!("TS", 0x0C),
+    register!("CRT_UPADDR", 0x10),
+    register!("CRT_LWADDR", 0x14),
+    register!(
+        "CTL",
+        0x18,
+        [
+            field!(DMA_CHALX_CTL::CHALX_EN),
+            field!(DMA_CHALX_CTL::CHALX_SRST),
+            field!(
+                DMA_CHALX_CTL::CHALX_MODE,
+                [
+                    (DMA_CHALX_CTL::CHALX_MODE::Value::Tx, "Tx"),
+                    (DMA_CHALX_CTL::CHALX_MODE::Value::Rx, "Rx")
+                ]
+            ),
+        ]
+    ),
+    register!(
+        "STS",
+        0x1C,
+        [
+            field!(DMA_CHALX_STS::FIFO_FULL),
+            field!(DMA_CHALX_STS::FIFO_EMPTY),
+        ]
+    ),
+    register!(
+        "TIMEOUT_CNT",
+        0x20,
+        [
+            field!(DMA_CHALX_TIMEOUT_CNT::TIMEOUT_CNT),
+            field!(DMA_CHALX_TIMEOUT_CNT::TIMEOUT_EN),
+        ]
+    ),
+];
+
+/// A register of a dump
+#[derive(Debug, Clone, Copy)]
+pub struct Register {
+    pub info: &'static RegisterInfo,
+    /// Channel of a channel register
+    pub channel: Option<usize>,
+    /// Offset from the window base
+    pub offset: usize,
+    /// Value, `None` if the dump does not cover the register
+    pub value: Option<u32>,
+}
+
+/// Error parsing a hex dump
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// A word is not valid hexadecimal
+    InvalidHex { line: usize },
+    /// A word lies outside the register window
+    OutOfWindow { line: usize },
+    /// The dump contains no word
+    Empty,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::InvalidHex { line } => write!(f, "line {line}: invalid hex word"),
+            ParseError::OutOfWindow { line } => {
+                write!(
+                    f,
+                    "line {line}: word outside the 0x{WINDOW_SIZE:x}-byte window"
+                )
+            }
+            ParseError::Empty => write!(f, "no register words in dump"),
+        }
+    }
+}
+
+/// Contents of the register window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dump {
+    words: Vec<Option<u32>>,
+}
+
+impl Dump {
+    /// Read a binary dump of little-endian words
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let mut words = alloc::vec![None; WINDOW_SIZE / 4];
+        for (word, chunk) in words.iter_mut().zip(bytes.as_chunks::<4>().0) {
+            *word = Some(u32::from_le_bytes(*chunk));
+        }
+        Self { words }
+    }
+
+    /// Parse a hex text dump of 32-bit words
+    ///
+    /// Lines may start with an address followed by a colon, as printed by
+    /// U-Boot `md.l`; words are then placed relative to the first address,
+    /// and the ASCII column, set apart by several spaces, is ignored. Without
+    /// addresses, words are taken in order from offset 0. Anything after the
+    /// last hex word of a line is ignored, as are lines starting with `#`.
+    pub fn parse_hex(text: &str) -> Result<Self, ParseError> {
+        let mut words = alloc::vec![None; WINDOW_SIZE / 4];
+        let mut base = None;
+        let mut next = 0usize;
+        let mut found = false;
+
+        for (i, line) in text.lines().enumerate() {
+            let line_no = i + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut words_text = line;
+            let first = line.split_whitespace().next().unwrap_or_default();
+            if let Some(addr) = first.strip_suffix(':') {
+                let rest = &line[first.len()..];
+                let addr = parse_hex(addr, 16).ok_or(ParseError::InvalidHex { line: line_no })?;
+                let base = *base.get_or_insert(addr);
+                next = addr
+                    .checked_sub(base)
+                    .and_then(|offset| usize::try_from(offset).ok())
+                    .ok_or(ParseError::OutOfWindow { line: line_no })?;
+                // `md.l` separates the ASCII column, which may look like hex
+                // ("beef"), by at least four spaces
+                let rest = rest.trim_start();
+                words_text = rest.split_once("    ").map_or(rest, |(hex, _)| hex);
+            }
+
+            for token in words_text.split_whitespace() {
+                let Some(word) = parse_hex(token, 8).map(|w| w as u32) else {
+                    break;
+                };
+                let slot = words
+                    .get_mut(next / 4)
+                    .ok_or(ParseError::OutOfWindow { line: line_no })?;
+                *slot = Some(word);
+                next += 4;
+                found = true;
+            }
+        }
+
+        if !found {
+            return Err(ParseError::Empty);
+        }
+        Ok(Self { words })
+    }
+
+    /// Word at byte `offset`, if the dump covers it
+    pub fn word(&self, offset: usize) -> Option<u32> {
+        self.words.get(offset / 4).copied().flatten()
+    }
+
+    /// Registers whose value differs in `other`, paired as (self, other)
+    ///
+    /// A register covered by only one of the dumps counts as changed.
+    pub fn diff<'a>(&'a self, other: &'a Dump) -> impl Iterator<Item = (Register, Register)> + 'a {
+        self.registers()
+            .zip(other.registers())
+            .filter(|(a, b)| a.value != b.value)
+    }
+
+    /// All registers of the window, global ones first, then each channel
+    pub fn registers(&self) -> impl Iterator<Item = Register> + '_ {
+        let global = GLOBAL_REGISTERS
+            .iter()
+            .map(|info| (info, None, info.offset));
+        let channels = (0..CHANNELS).flat_map(|n| {
+            let base = channel_offset(n);
+            CHANNEL_REGISTERS
+                .iter()
+                .map(move |info| (info, Some(n), base + info.offset))
+        });
+        global
+            .chain(channels)
+            .filter(|(_, _, offset)| *offset < WINDOW_SIZE)
+            .map(|(info, channel, offset)| Register {
+                info,
+                channel,
+                offset,
+                value: self.word(offset),
+            })
+    }
+}
+
+/// Parse a hex number of at most `digits` digits, with optional `0x` prefix
+fn parse_hex(token: &str, digits: usize) -> Option<u64> {
+    let hex = token
+        .strip_prefix("0x")
+        .or_else(|| token.strip_prefix("0X"))
+        .unwrap_or(token);
+    if hex.is_empty() || hex.len() > digits {
+        return None;
+    }
+    u64::from_str_radix(hex, 16).ok()
+}
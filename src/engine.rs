@@ -0,0 +1,143 @@
+//! Controller-agnostic DMA engine traits
+//!
+//! Peripheral drivers written against [`DmaEngine`] and [`DmaSlaveChannel`]
+//! work with any DMA controller implementing them, including a mock engine in
+//! their own tests.
+
+use crate::{Channel, ChannelConfig, DDMA, DdmaError};
+
+/// State of the transfer on a slave channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferStatus {
+    /// No transfer submitted
+    Idle,
+    /// The submitted transfer is running
+    InProgress,
+    /// The submitted transfer completed; a cyclic transfer keeps running
+    Complete,
+}
+
+/// A DMA controller handing out slave channels
+pub trait DmaEngine {
+    /// Channel type returned by [`DmaEngine::request_channel`]
+    type Channel: DmaSlaveChannel<Error = Self::Error>;
+    /// Error type of the engine and its channels
+    type Error: core::fmt::Debug;
+
+    /// Request a channel connected to a peripheral request line, with a
+    /// buffer of `config.blk_size` bytes
+    fn request_channel(&self, config: &ChannelConfig) -> Result<Self::Channel, Self::Error>;
+}
+
+/// A DMA channel connected to a peripheral
+pub trait DmaSlaveChannel {
+    /// Error type of the channel
+    type Error: core::fmt::Debug;
+
+    /// Size of the channel buffer in bytes
+    fn capacity(&self) -> usize;
+
+    /// Copy `data` into the channel buffer at `offset`
+    fn write_buffer(&mut self, offset: usize, data: &[u8]) -> Result<(), Self::Error>;
+
+    /// Copy from the channel buffer at `offset` into `data`
+    fn read_buffer(&self, offset: usize, data: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// Prepare a single transfer of the first `len` bytes of the buffer
+    fn prepare_single(&mut self, len: usize) -> Result<(), Self::Error>;
+
+    /// Prepare a transfer of the first `len` bytes of the buffer that restarts
+    /// each time it completes
+    ///
+    /// The DDMA has no auto-reload: the transfer is restarted when
+    /// [`DmaSlaveChannel::status`] consumes its completion, so `status` must
+    /// be polled, or called on each interrupt of the channel, to keep it
+    /// running.
+    fn prepare_cyclic(&mut self, len: usize) -> Result<(), Self::Error>;
+
+    /// Start the prepared transfer
+    fn submit(&mut self) -> Result<(), Self::Error>;
+
+    /// Poll the state of the submitted transfer
+    fn status(&mut self) -> TransferStatus;
+
+    /// Stop the channel, aborting any transfer in progress
+    fn terminate(&mut self);
+}
+
+impl DmaEngine for DDMA {
+    type Channel = Channel;
+    type Error = DdmaError;
+
+    fn request_channel(&self, config: &ChannelConfig) -> Result<Channel, DdmaError> {
+        DDMA::request_channel(self, config.clone()).ok_or(DdmaError::NoChannel)
+    }
+}
+
+impl DmaSlaveChannel for Channel {
+    type Error = DdmaError;
+
+    fn capacity(&self) -> usize {
+        Channel::capacity(self)
+    }
+
+    fn write_buffer(&mut self, offset: usize, data: &[u8]) -> Result<(), DdmaError> {
+        if offset
+            .checked_add(data.len())
+            .is_none_or(|end| end > self.capacity())
+        {
+            return Err(DdmaError::InvalidSize);
+        }
+        let buff = self.try_buff_mut().ok_or(DdmaError::NoBuffer)?;
+        // `DVec::set` flushes the cache for every byte, stage the whole buffer
+        // and flush it once instead
+        let mut staged = buff.as_ref().to_vec();
+        staged[offset..offset + data.len()].copy_from_slice(data);
+        buff.copy_from_slice(&staged);
+        Ok(())
+    }
+
+    fn read_buffer(&self, offset: usize, data: &mut [u8]) -> Result<(), DdmaError> {
+        if offset
+            .checked_add(data.len())
+            .is_none_or(|end| end > self.capacity())
+        {
+            return Err(DdmaError::InvalidSize);
+        }
+        let buff = self.try_buff().ok_or(DdmaError::NoBuffer)?;
+        for (i, b) in data.iter_mut().enumerate() {
+            *b = buff.get(offset + i).unwrap_or_default();
+        }
+        Ok(())
+    }
+
+    fn prepare_single(&mut self, len: usize) -> Result<(), DdmaError> {
+        self.prepare(len, false)
+    }
+
+    fn prepare_cyclic(&mut self, len: usize) -> Result<(), DdmaError> {
+        self.prepare(len, true)
+    }
+
+    fn submit(&mut self) -> Result<(), DdmaError> {
+        if self.is_running() {
+            return Err(DdmaError::Busy);
+        }
+        self.active();
+        Ok(())
+    }
+
+    fn status(&mut self) -> TransferStatus {
+        if self.poll_complete() {
+            TransferStatus::Complete
+        } else if self.is_submitted() {
+            TransferStatus::InProgress
+        } else {
+            TransferStatus::Idle
+        }
+    }
+
+    fn terminate(&mut self) {
+        self.deactive();
+    }
+}
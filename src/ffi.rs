@@ -0,0 +1,391 @@
+//! C API mirroring the `fddma` driver of the Phytium standalone SDK
+//!
+//! C code written against the SDK keeps its call sequence (initialize,
+//! configure channel, activate, start, interrupt handler, deconfigure) and
+//! links against this crate instead. The matching declarations are in
+//! `include/fddma.h`, generated with `cbindgen --config cbindgen.toml`.
+//!
+//! The API is source-compatible only, not ABI-compatible with the SDK: the
+//! error values and the layout of `FDdma` and the configuration structures
+//! differ. C code must be rebuilt against `include/fddma.h`, compare errors
+//! against the constants, and not access `FDdma` fields other than those
+//! declared there.
+
+#![allow(non_snake_case)]
+
+use alloc::boxed::Box;
+use core::{ffi::c_void, ptr::NonNull};
+
+use crate::{
+    Channel, DDMA, DdmaError, DmaChannelConfig, DmaDirection, DmaTransfer, IrqHandler,
+    reg::DdmaRegister,
+};
+
+/// SDK error code
+pub type FError = u32;
+
+/// Success
+pub const FDDMA_SUCCESS: FError = 0;
+/// The instance is not initialized or an argument is null
+pub const FDDMA_ERR_NOT_INIT: FError = 1;
+/// The channel is already configured
+pub const FDDMA_ERR_CHAN_BINDED: FError = 2;
+/// The channel is running
+pub const FDDMA_ERR_CHAN_RUNNING: FError = 3;
+/// Transfer size is not a multiple of 4 bytes
+pub const FDDMA_ERR_INVALID_TRANS_SIZE: FError = 4;
+/// Wait for the hardware timed out
+pub const FDDMA_ERR_WAIT_TIMEOUT: FError = 5;
+/// DDR address is not aligned to 4 bytes
+pub const FDDMA_ERR_INVALID_DDR_ADDR: FError = 6;
+/// The instance is already initialized
+pub const FDDMA_ERR_IS_USED: FError = 7;
+/// Channel index, slave ID, direction or device address is out of range
+pub const FDDMA_ERR_INVALID_INPUT: FError = 8;
+
+/// Transfer direction of a channel (`FDdmaChanRequst`)
+pub type FDdmaChanRequst = u32;
+/// Device to memory
+pub const FDDMA_CHAN_REQ_RX: FDdmaChanRequst = 0;
+/// Memory to device
+pub const FDDMA_CHAN_REQ_TX: FDdmaChanRequst = 1;
+
+/// Channel event (`FDdmaChanEvt`)
+pub type FDdmaChanEvt = u32;
+/// The channel finished its transfer
+pub const FDDMA_CHAN_EVT_REQ_DONE: FDdmaChanEvt = 0;
+
+/// Channel event callback, called from `FDdmaIrqHandler` with the channel
+/// index and the registered argument
+pub type FDdmaChanEvtHandler = Option<unsafe extern "C" fn(chan_idx: u32, args: *mut c_void)>;
+
+/// Controller configuration
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct FDdmaConfig {
+    /// Controller index
+    pub id: u32,
+    /// Mapped base address of the controller registers
+    pub base_addr: usize,
+    /// Interrupt number
+    pub irq_num: u32,
+    /// Interrupt priority
+    pub irq_prority: u32,
+}
+
+/// Channel configuration
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct FDdmaChanConfig {
+    /// Peripheral request line
+    pub slave_id: u32,
+    /// `FDDMA_CHAN_REQ_RX` or `FDDMA_CHAN_REQ_TX`
+    pub req_mode: FDdmaChanRequst,
+    /// DDR bus address, source for TX and destination for RX
+    pub ddr_addr: usize,
+    /// Peripheral FIFO address
+    pub dev_addr: u32,
+    /// Transfer length in bytes
+    pub trans_len: u32,
+    /// Hardware timeout count, 0 disables the timeout
+    pub timeout: u32,
+}
+
+/// Controller instance, allocated by the caller
+#[repr(C)]
+pub struct FDdma {
+    /// Configuration given to `FDdmaCfgInitialize`
+    pub config: FDdmaConfig,
+    /// Non-zero once initialized
+    pub is_ready: u32,
+    /// Driver state, owned by this crate
+    pub priv_: *mut c_void,
+}
+
+struct Instance {
+    dma: DDMA,
+    irq: IrqHandler,
+    chans: [Option<Channel>; DdmaRegister::MAX_CHANNELS],
+    evt: [(FDdmaChanEvtHandler, *mut c_void); DdmaRegister::MAX_CHANNELS],
+}
+
+fn error_code(err: DdmaError) -> FError {
+    match err {
+        DdmaError::ChannelInUse => FDDMA_ERR_CHAN_BINDED,
+        DdmaError::Busy => FDDMA_ERR_CHAN_RUNNING,
+        DdmaError::InvalidSize => FDDMA_ERR_INVALID_TRANS_SIZE,
+        DdmaError::Timeout => FDDMA_ERR_WAIT_TIMEOUT,
+        DdmaError::Unaligned => FDDMA_ERR_INVALID_DDR_ADDR,
+        _ => FDDMA_ERR_INVALID_INPUT,
+    }
+}
+
+/// Get the driver state of an initialized instance
+///
+/// # Safety
+///
+/// `instance` must be null or point to a valid `FDdma`.
+unsafe fn instance<'a>(instance: *mut FDdma) -> Result<&'a mut Instance, FError> {
+    let instance = unsafe { instance.as_mut() }.ok_or(FDDMA_ERR_NOT_INIT)?;
+    if instance.is_ready == 0 || instance.priv_.is_null() {
+        return Err(FDDMA_ERR_NOT_INIT);
+    }
+    Ok(unsafe { &mut *instance.priv_.cast::<Instance>() })
+}
+
+/// Get a configured channel of an initialized instance
+///
+/// # Safety
+///
+/// Same as [`instance`].
+unsafe fn channel<'a>(instance: *mut FDdma, chan_idx: u32) -> Result<&'a mut Channel, FError> {
+    let inner = unsafe { self::instance(instance) }?;
+    inner
+        .chans
+        .get_mut(chan_idx as usize)
+        .ok_or(FDDMA_ERR_INVALID_INPUT)?
+        .as_mut()
+        .ok_or(FDDMA_ERR_NOT_INIT)
+}
+
+fn result(r: Result<(), FError>) -> FError {
+    r.err().unwrap_or(FDDMA_SUCCESS)
+}
+
+/// Initialize `instance` for the controller described by `config`, resetting
+/// the controller
+///
+/// # Safety
+///
+/// `instance` must point to a valid `FDdma` and `config` to a valid
+/// `FDdmaConfig` whose `base_addr` maps the controller registers.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn FDdmaCfgInitialize(
+    instance: *mut FDdma,
+    config: *const FDdmaConfig,
+) -> FError {
+    let (Some(instance), Some(config)) = (unsafe { instance.as_mut() }, unsafe { config.as_ref() })
+    else {
+        return FDDMA_ERR_NOT_INIT;
+    };
+    if instance.is_ready != 0 {
+        return FDDMA_ERR_IS_USED;
+    }
+    let Some(base) = NonNull::new(config.base_addr as *mut u8) else {
+        return FDDMA_ERR_INVALID_INPUT;
+    };
+
+    let dma = DDMA::new(base);
+    dma.reset();
+    let irq = dma.irq_handler();
+    let inner = Box::new(Instance {
+        dma,
+        irq,
+        chans: Default::default(),
+        evt: [(None, core::ptr::null_mut()); DdmaRegister::MAX_CHANNELS],
+    });
+
+    instance.config = *config;
+    instance.priv_ = Box::into_raw(inner).cast();
+    instance.is_ready = 1;
+    FDDMA_SUCCESS
+}
+
+/// Release all channels of `instance` and stop the controller
+///
+/// # Safety
+///
+/// `instance` must be null or point to a valid `FDdma`, and no other call on
+/// it may run concurrently.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn FDdmaDeInitialize(instance: *mut FDdma) {
+    if unsafe { self::instance(instance) }.is_err() {
+        return;
+    }
+    let instance = unsafe { &mut *instance };
+    let inner = unsafe { Box::from_raw(instance.priv_.cast::<Instance>()) };
+    inner.dma.disable();
+    drop(inner);
+    instance.priv_ = core::ptr::null_mut();
+    instance.is_ready = 0;
+}
+
+/// Bind channel `chan_idx` to a peripheral and program its transfer
+///
+/// # Safety
+///
+/// `instance` must be null or point to a valid `FDdma`, `config` must be null
+/// or point to a valid `FDdmaChanConfig`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn FDdmaChanConfigure(
+    instance: *mut FDdma,
+    chan_idx: u32,
+    config: *const FDdmaChanConfig,
+) -> FError {
+    result((|| {
+        let inner = unsafe { self::instance(instance) }?;
+        let config = unsafe { config.as_ref() }.ok_or(FDDMA_ERR_NOT_INIT)?;
+        let slot = inner
+            .chans
+            .get_mut(chan_idx as usize)
+            .ok_or(FDDMA_ERR_INVALID_INPUT)?;
+        if slot.is_some() {
+            return Err(FDDMA_ERR_CHAN_BINDED);
+        }
+
+        let direction = match config.req_mode {
+            FDDMA_CHAN_REQ_RX => DmaDirection::DeviceToMemory,
+            FDDMA_CHAN_REQ_TX => DmaDirection::MemoryToDevice,
+            _ => return Err(FDDMA_ERR_INVALID_INPUT),
+        };
+        let chan_config = DmaChannelConfig {
+            channel: chan_idx as u8,
+            peripheral_id: u8::try_from(config.slave_id).map_err(|_| FDDMA_ERR_INVALID_INPUT)?,
+            direction,
+            timeout_enable: config.timeout != 0,
+            timeout_count: config.timeout,
+            irq: true,
+        };
+        let transfer = DmaTransfer {
+            mem_addr: config.ddr_addr as u64,
+            dev_addr: config.dev_addr,
+            size: config.trans_len as usize,
+        };
+
+        let chan = inner
+            .dma
+            .prepare_transfer(&chan_config, &transfer)
+            .map_err(error_code)?;
+        *slot = Some(chan);
+        Ok(())
+    })())
+}
+
+/// Unbind channel `chan_idx`, stopping it if running
+///
+/// # Safety
+///
+/// `instance` must be null or point to a valid `FDdma`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn FDdmaChanDeconfigure(instance: *mut FDdma, chan_idx: u32) -> FError {
+    result((|| {
+        let inner = unsafe { self::instance(instance) }?;
+        let slot = inner
+            .chans
+            .get_mut(chan_idx as usize)
+            .ok_or(FDDMA_ERR_INVALID_INPUT)?;
+        slot.take().ok_or(FDDMA_ERR_NOT_INIT)?;
+        Ok(())
+    })())
+}
+
+/// Start the transfer of channel `chan_idx`
+///
+/// # Safety
+///
+/// `instance` must be null or point to a valid `FDdma`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn FDdmaChanActive(instance: *mut FDdma, chan_idx: u32) -> FError {
+    result((|| {
+        let chan = unsafe { channel(instance, chan_idx) }?;
+        if chan.is_running() {
+            return Err(FDDMA_ERR_CHAN_RUNNING);
+        }
+        chan.active();
+        Ok(())
+    })())
+}
+
+/// Stop the transfer of channel `chan_idx`
+///
+/// # Safety
+///
+/// `instance` must be null or point to a valid `FDdma`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn FDdmaChanDeactive(instance: *mut FDdma, chan_idx: u32) -> FError {
+    result((|| {
+        unsafe { channel(instance, chan_idx) }?.deactive();
+        Ok(())
+    })())
+}
+
+/// Enable the controller
+///
+/// # Safety
+///
+/// `instance` must be null or point to a valid `FDdma`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn FDdmaStart(instance: *mut FDdma) -> FError {
+    result((|| {
+        unsafe { self::instance(instance) }?.dma.enable();
+        Ok(())
+    })())
+}
+
+/// Disable the controller
+///
+/// # Safety
+///
+/// `instance` must be null or point to a valid `FDdma`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn FDdmaStop(instance: *mut FDdma) -> FError {
+    result((|| {
+        unsafe { self::instance(instance) }?.dma.disable();
+        Ok(())
+    })())
+}
+
+/// Register `handler` for event `evt` of channel `chan_idx`
+///
+/// # Safety
+///
+/// `instance` must be null or point to a valid `FDdma`, `handler` must be
+/// safe to call from interrupt context with `handler_arg`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn FDdmaRegisterChanEvtHandler(
+    instance: *mut FDdma,
+    chan_idx: u32,
+    evt: FDdmaChanEvt,
+    handler: FDdmaChanEvtHandler,
+    handler_arg: *mut c_void,
+) {
+    let Ok(inner) = (unsafe { self::instance(instance) }) else {
+        return;
+    };
+    if evt != FDDMA_CHAN_EVT_REQ_DONE {
+        return;
+    }
+    if let Some(slot) = inner.evt.get_mut(chan_idx as usize) {
+        *slot = (handler, handler_arg);
+    }
+}
+
+/// Interrupt handler, `args` being the `FDdma` instance
+///
+/// Completed channels are stopped and their `FDDMA_CHAN_EVT_REQ_DONE`
+/// handler called.
+///
+/// # Safety
+///
+/// `args` must be null or point to a valid `FDdma`, and channel calls must
+/// not run concurrently on another core, as with the SDK driver.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn FDdmaIrqHandler(_vector: i32, args: *mut c_void) {
+    let Ok(inner) = (unsafe { self::instance(args.cast()) }) else {
+        return;
+    };
+    let Some(completed) = inner.irq.handle_irq() else {
+        return;
+    };
+    for n in 0..DdmaRegister::MAX_CHANNELS {
+        if !completed.is_channel_completed(n as u8) {
+            continue;
+        }
+        if let Some(chan) = inner.chans[n].as_mut() {
+            chan.poll_complete();
+        }
+        if let (Some(handler), arg) = inner.evt[n] {
+            unsafe { handler(n as u32, arg) };
+        }
+    }
+}
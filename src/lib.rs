@@ -1,18 +1,71 @@
 #![no_std]
 #![recursion_limit = "512"]
 
-use core::ptr::NonNull;
+use alloc::{boxed::Box, sync::Arc};
+use core::{
+    hint::spin_loop,
+    ptr::NonNull,
+    sync::atomic::{AtomicU8, Ordering},
+    time::Duration,
+};
+use dma_api::DVec;
 use log::{debug, trace};
 use tock_registers::interfaces::{ReadWriteable, Readable, Writeable};
 
 extern crate alloc;
 
+mod adopt;
+mod bounce;
 mod chan;
+mod context;
+#[cfg(feature = "decode")]
+pub mod decode;
+mod engine;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod lock;
+mod manager;
+#[cfg(any(feature = "mmio-trace", feature = "sim"))]
+mod mmio;
+mod pair;
+mod pingpong;
 mod reg;
-
-pub use chan::{Channel, ChannelConfig};
-
-use crate::reg::{DMA_STAT, DdmaRegister, DmaChannelRegisters};
+mod selftest;
+#[cfg(feature = "sim")]
+pub mod sim;
+pub mod soc;
+#[cfg(feature = "stats")]
+mod stats;
+pub mod time;
+#[cfg(feature = "mmio-trace")]
+pub mod trace;
+mod translate;
+#[cfg(feature = "embedded-io")]
+mod uart;
+mod watchdog;
+
+pub use adopt::ChannelState;
+pub use bounce::{SliceTransfer, TailPolicy};
+pub use chan::{Channel, ChannelConfig, FifoStatus, RetryPolicy};
+pub use context::{ChannelContext, DdmaContext};
+pub use engine::{DmaEngine, DmaSlaveChannel, TransferStatus};
+pub use manager::{DdmaManager, ManagerIrqHandler};
+pub use pair::ChannelPair;
+pub use pingpong::{DoubleBuffer, DoubleBufferReader, FilledBuffer};
+pub use selftest::{CheckResult, RegisterMismatch, SelfTestReport};
+pub use soc::{DmaRequest, RawRequest};
+#[cfg(feature = "stats")]
+pub use stats::ChannelStats;
+pub use translate::AddressTranslation;
+#[cfg(feature = "embedded-io")]
+pub use uart::DmaUart;
+pub use watchdog::{Stall, StallAction, Watchdog, WatchdogStatus};
+
+use crate::{
+    lock::IrqLock,
+    reg::{DMA_STAT, DdmaRegister, DmaChannelRegisters},
+    time::{Clock, Deadline, WaitBudget},
+};
 
 /// DMA transfer direction
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -23,7 +76,47 @@ pub enum DmaDirection {
     DeviceToMemory,
 }
 
-/// DMA channel configuration
+/// DDMA driver errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DdmaError {
+    /// No free channel is available
+    NoChannel,
+    /// Channel number is not between 0 and 7
+    InvalidChannel,
+    /// Peripheral slave ID is not between 0 and 31
+    InvalidSlaveId,
+    /// The channel is already bound to a peripheral
+    ChannelInUse,
+    /// The channel is running a transfer
+    Busy,
+    /// Transfer size is not a multiple of 4 bytes or exceeds the buffer
+    InvalidSize,
+    /// Memory address is not aligned to 4 bytes
+    Unaligned,
+    /// The channel has no driver-owned buffer
+    NoBuffer,
+    /// The hardware did not respond within the timeout
+    Timeout,
+    /// Channel direction does not match its use
+    InvalidDirection,
+    /// No transfer was started
+    NoTransfer,
+    /// DMA buffer allocation failed
+    NoMemory,
+    /// Translated device address does not fit in 32 bits
+    InvalidAddress,
+    /// The channel is not bound to a peripheral
+    NotBound,
+    /// The manager already has a controller with this index
+    ControllerInUse,
+    /// Received data was overwritten before it was read
+    Overrun,
+}
+
+/// Binding of a given channel for a caller-provided memory region
+///
+/// Used with [`DmaTransfer`] by [`DDMA::prepare_transfer`]. Channels with a
+/// driver-owned buffer are configured with [`ChannelConfig`] instead.
 #[derive(Debug, Clone)]
 pub struct DmaChannelConfig {
     /// Channel number (0-7)
@@ -36,39 +129,205 @@ pub struct DmaChannelConfig {
     pub timeout_enable: bool,
     /// Timeout count
     pub timeout_count: u32,
+    /// Unmask the transfer complete interrupt of the channel
+    pub irq: bool,
 }
 
 /// DMA transfer descriptor
 #[derive(Debug, Clone)]
 pub struct DmaTransfer {
-    /// Source address (memory address for TX, device address for RX)
-    pub src_addr: u64,
-    /// Destination address (device address for TX, memory address for RX)
-    pub dst_addr: u32,
-    /// Transfer size in bytes
-    pub size: u32,
+    /// Memory bus address (source for TX, destination for RX)
+    pub mem_addr: u64,
+    /// Device address (destination for TX, source for RX)
+    pub dev_addr: u32,
+    /// Transfer size in bytes, split into several hardware blocks if larger
+    /// than `DmaChannelRegisters::MAX_BLOCK_SIZE`
+    pub size: usize,
+}
+
+impl DmaTransfer {
+    /// Check the transfer against the alignment rules of the controller
+    pub fn validate(&self) -> Result<(), DdmaError> {
+        // Check DDR address alignment (following C reference)
+        if !self.mem_addr.is_multiple_of(4) {
+            trace!(
+                "DDR addr 0x{:x} must be aligned with 4 bytes.",
+                self.mem_addr
+            );
+            return Err(DdmaError::Unaligned);
+        }
+
+        // Check transfer size alignment (following C reference)
+        if self.size < 4 || !self.size.is_multiple_of(4) {
+            trace!(
+                "Invalid transfer size {} bytes, it should be an integer multiple of 4 bytes.",
+                self.size
+            );
+            return Err(DdmaError::InvalidSize);
+        }
+
+        Ok(())
+    }
 }
 
 /// DDMA Controller
+///
+/// The controller can be shared between drivers and cores (e.g. through an
+/// `Arc<DDMA>`): read-modify-write of the registers shared by all channels is
+/// serialized by an internal lock taken with local interrupts masked.
 pub struct DDMA {
     reg: NonNull<reg::DdmaRegister>,
+    shared: Arc<Shared>,
+}
+
+/// State shared between `DDMA`, its channels and its `IrqHandler`
+#[derive(Default)]
+pub(crate) struct Shared {
+    lock: IrqLock,
+    /// Completions acknowledged by the interrupt handler and not yet consumed
+    /// by the channel owning them
+    completed: AtomicU8,
+    /// Channels held by a `Channel` handle
+    owned: AtomicU8,
+    #[cfg(feature = "stats")]
+    stats: stats::Stats,
+    /// Address translation, identity if `None`
+    translation: Option<Box<dyn AddressTranslation>>,
+    /// Bound on busy-waits for the hardware
+    wait_budget: WaitBudget,
 }
 
+impl Shared {
+    /// Bus address of DMA memory
+    pub(crate) fn mem_addr(&self, addr: u64) -> u64 {
+        match &self.translation {
+            Some(t) => t.mem_addr(addr),
+            None => addr,
+        }
+    }
+
+    pub(crate) fn has_translation(&self) -> bool {
+        self.translation.is_some()
+    }
+
+    /// Bus address of a peripheral register, checked to fit in DEV_ADDR
+    pub(crate) fn dev_addr(&self, addr: u32) -> Result<u32, DdmaError> {
+        match &self.translation {
+            Some(t) => translate::check_dev_addr(t.dev_addr(addr as u64)),
+            None => Ok(addr),
+        }
+    }
+
+    /// Number of polls a busy-wait on the hardware may take
+    pub(crate) fn wait_polls(&self) -> u32 {
+        self.wait_budget.get()
+    }
+
+    /// Consume a completion latched by the interrupt handler
+    pub(crate) fn take_completed(&self, channel: u8) -> bool {
+        let bit = 1 << channel;
+        self.completed.fetch_and(!bit, Ordering::AcqRel) & bit != 0
+    }
+
+    /// Mark a channel as held by a `Channel` handle
+    pub(crate) fn claim(&self, channel: u8) {
+        self.owned.fetch_or(1 << channel, Ordering::AcqRel);
+    }
+
+    /// Mark a channel as no longer held by a `Channel` handle
+    pub(crate) fn release(&self, channel: u8) {
+        self.owned.fetch_and(!(1 << channel), Ordering::AcqRel);
+    }
+
+    /// Check if a channel is held by a `Channel` handle
+    pub(crate) fn is_owned(&self, channel: u8) -> bool {
+        self.owned.load(Ordering::Acquire) & (1 << channel) != 0
+    }
+
+    /// Check if any channel is held by a `Channel` handle
+    pub(crate) fn any_owned(&self) -> bool {
+        self.owned.load(Ordering::Acquire) != 0
+    }
+
+    /// Drop a stale latched completion before a new transfer starts
+    pub(crate) fn discard_completed(&self, channel: u8) {
+        self.completed.fetch_and(!(1 << channel), Ordering::AcqRel);
+    }
+}
+
+// Shared registers are only modified under `Shared::lock`, per-channel registers are
+// owned by the `Channel` handles.
+unsafe impl Send for DDMA {}
+unsafe impl Sync for DDMA {}
+
 impl DDMA {
+    /// Default number of polls of a busy-wait on the hardware, see
+    /// [`DDMA::set_wait_budget`]
+    pub const DEFAULT_WAIT_BUDGET: u32 = 10_000_000;
+
     /// Create a new DDMA instance
     pub fn new(base_addr: NonNull<u8>) -> Self {
         Self {
             reg: base_addr.cast(),
+            shared: Arc::default(),
         }
     }
 
+    /// Create a new DDMA instance programming addresses mapped by `translation`
+    pub fn with_translation(
+        base_addr: NonNull<u8>,
+        translation: impl AddressTranslation + 'static,
+    ) -> Self {
+        Self {
+            reg: base_addr.cast(),
+            shared: Arc::new(Shared {
+                translation: Some(Box::new(translation)),
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Bound the busy-waits on the hardware that take no timeout to `polls`
+    /// reads of the awaited status
+    ///
+    /// Such waits, like stopping a channel before it is rebound or flushing a
+    /// `DmaUart`, fail with `DdmaError::Timeout` once the budget is spent
+    /// instead of hanging on wedged hardware. The budget applies to the
+    /// channels of this controller, including the existing ones.
+    pub fn set_wait_budget(&self, polls: u32) {
+        self.shared.wait_budget.set(polls);
+    }
+
+    /// Number of polls a busy-wait on the hardware may take
+    pub fn wait_budget(&self) -> u32 {
+        self.shared.wait_polls()
+    }
+
     fn reg(&self) -> &reg::DdmaRegister {
         unsafe { self.reg.as_ref() }
     }
 
+    fn channel_reg(&self, n: u8) -> NonNull<DmaChannelRegisters> {
+        // Calculate the address offset for the specified channel
+        let channel_offset =
+            DdmaRegister::CHANNEL_BASE_OFFSET + (n as usize) * DdmaRegister::CHANNEL_REGISTER_SIZE;
+
+        // Get the base address of the register structure
+        let base_addr = self.reg.as_ptr() as usize;
+        let channel_addr = base_addr + channel_offset;
+
+        unsafe { NonNull::new_unchecked(channel_addr as *mut DmaChannelRegisters) }
+    }
+
     /// Initialize the DMA controller
-    pub fn reset(&mut self) {
-        let reg = unsafe { self.reg.as_mut() };
+    pub fn reset(&self) {
+        let _guard = self.shared.lock.lock();
+        self.reset_locked();
+    }
+
+    /// Body of [`DDMA::reset`], for callers already holding the lock
+    fn reset_locked(&self) {
+        let reg = self.reg();
 
         // Disable DDMA controller first
         reg.dma_ctl.write(reg::DMA_CTL::DMA_ENABLE::CLEAR);
@@ -87,6 +346,9 @@ impl DDMA {
             reg.clear_channel_complete(chan_id);
             // Reset channel configuration
             reg.set_channel_config(chan_id, 0, false);
+            // The transfer in flight, if any, is aborted
+            #[cfg(feature = "stats")]
+            self.shared.stats.deactivated(chan_id as u8);
         }
 
         // Perform software reset
@@ -95,59 +357,118 @@ impl DDMA {
         reg.dma_mask_int.set(u32::MAX);
     }
 
-    pub fn enable(&mut self) {
+    pub fn enable(&self) {
+        let _guard = self.shared.lock.lock();
         self.reg()
             .dma_mask_int
             .modify(reg::DMA_MASK_INT::GLOBAL_EN::CLEAR);
         self.reg().dma_ctl.modify(reg::DMA_CTL::DMA_ENABLE::SET);
     }
 
-    pub fn disable(&mut self) {
-        let reg = unsafe { self.reg.as_mut() };
-        reg.dma_ctl.write(reg::DMA_CTL::DMA_ENABLE::CLEAR);
+    pub fn disable(&self) {
+        let _guard = self.shared.lock.lock();
+        self.reg().dma_ctl.write(reg::DMA_CTL::DMA_ENABLE::CLEAR);
     }
 
-    pub fn new_channel(&mut self, n: u8, config: ChannelConfig) -> Option<Channel> {
+    /// Bind channel `n` to a peripheral and program it with a new buffer of
+    /// `config.blk_size` bytes
+    ///
+    /// The controller is stopped while the channel is configured (following C
+    /// reference) and restarted afterwards if it was running, so channels
+    /// owned by other drivers keep going.
+    pub fn new_channel(&self, n: u8, config: ChannelConfig) -> Option<Channel> {
         assert!(n <= 7, "Channel number must be between 0 and 7");
         assert!(
             config.slave_id <= 31,
             "Peripheral ID must be between 0 and 31"
         );
+
+        // Create the buffer first to get its bus address
+        let buff = DVec::zeros(config.blk_size, 128, dma_api::Direction::Bidirectional)?;
+        let transfer = DmaTransfer {
+            mem_addr: buff.bus_addr(),
+            dev_addr: config.dev_addr,
+            size: config.blk_size,
+        };
+
+        self.bind_channel(&config.to_channel_config(n), &transfer, Some(buff))
+            .ok()
+    }
+
+    /// Bind a channel to a peripheral and program it with a caller-provided
+    /// memory region
+    ///
+    /// The memory at `transfer.mem_addr` must stay valid and DMA-coherent (or
+    /// be maintained by the caller) while the channel uses it.
+    pub fn prepare_transfer(
+        &self,
+        config: &DmaChannelConfig,
+        transfer: &DmaTransfer,
+    ) -> Result<Channel, DdmaError> {
+        if config.channel as usize >= DdmaRegister::MAX_CHANNELS {
+            return Err(DdmaError::InvalidChannel);
+        }
+        if config.peripheral_id > 31 {
+            return Err(DdmaError::InvalidSlaveId);
+        }
+        self.bind_channel(config, transfer, None)
+    }
+
+    fn bind_channel(
+        &self,
+        config: &DmaChannelConfig,
+        transfer: &DmaTransfer,
+        buff: Option<DVec<u8>>,
+    ) -> Result<Channel, DdmaError> {
+        let n = config.channel;
         let channel = n as usize;
 
+        let _guard = self.shared.lock.lock();
+
         if self.reg().is_channel_bind(channel) {
             trace!("Channel {} is already in use", n);
-            return None; // Channel already in use
+            return Err(DdmaError::ChannelInUse);
         }
 
         // According to C reference: First stop DMA controller
-        self.disable();
-
-        // Calculate the address offset for the specified channel
-        let channel_offset =
-            DdmaRegister::CHANNEL_BASE_OFFSET + (n as usize) * DdmaRegister::CHANNEL_REGISTER_SIZE;
-
-        // Get the base address of the register structure
-        let base_addr = self.reg.as_ptr() as usize;
-        let channel_addr = base_addr + channel_offset;
-
-        let reg = unsafe { NonNull::new_unchecked(channel_addr as *mut DmaChannelRegisters) };
-
-        // Create channel first to get the buffer
-        let channel_result = Channel::new(n, reg, config.clone())?;
+        let was_enabled = self.reg().dma_ctl.is_set(reg::DMA_CTL::DMA_ENABLE);
+        self.reg().dma_ctl.write(reg::DMA_CTL::DMA_ENABLE::CLEAR);
+
+        let channel_result = Channel::new(
+            self.channel_reg(n),
+            self.reg,
+            config,
+            transfer,
+            buff,
+            self.shared.clone(),
+        );
 
-        // Configure channel selection and bind (following C reference sequence)
-        self.reg()
-            .set_channel_config(channel, config.slave_id as u32, true);
-        self.reg().set_channel_bind(channel, true);
+        if channel_result.is_ok() {
+            // Configure channel selection and bind (following C reference sequence)
+            self.reg()
+                .set_channel_config(channel, config.peripheral_id as u32, true);
+            self.reg().set_channel_bind(channel, true);
+            self.shared.claim(n);
+
+            if config.irq {
+                self.reg().set_channel_interrupt_mask(channel, false);
+            } else {
+                self.reg().set_channel_interrupt_mask(channel, true);
+            }
+        }
 
-        if config.irq {
-            self.reg().set_channel_interrupt_mask(channel, false);
-        } else {
-            self.reg().set_channel_interrupt_mask(channel, true);
+        if was_enabled {
+            self.reg().dma_ctl.modify(reg::DMA_CTL::DMA_ENABLE::SET);
         }
 
-        Some(channel_result)
+        channel_result
+    }
+
+    /// Bind the first free channel to a peripheral and program it
+    pub fn request_channel(&self, config: ChannelConfig) -> Option<Channel> {
+        (0..DdmaRegister::MAX_CHANNELS as u8)
+            .filter(|&n| !self.reg().is_channel_bind(n as usize))
+            .find_map(|n| self.new_channel(n, config.clone()))
     }
 
     /// Check if transfer is complete for a channel
@@ -160,6 +481,49 @@ impl DDMA {
         reg.is_channel_complete(channel as usize)
     }
 
+    /// Wait until one of the channels in `mask` completes, measured with the
+    /// generic timer
+    #[cfg(target_arch = "aarch64")]
+    pub fn wait_any(&self, mask: u8, timeout: Duration) -> Result<CompletedChannels, DdmaError> {
+        self.wait_any_with(&time::GenericTimer, mask, timeout)
+    }
+
+    /// Wait until one of the channels in `mask` completes, measured with `clock`
+    ///
+    /// Returns the completed channels of `mask`, both pending in DMA_STAT and
+    /// latched by the interrupt handler. The completions are not consumed, so
+    /// the owning channels still see them.
+    pub fn wait_any_with(
+        &self,
+        clock: &impl Clock,
+        mask: u8,
+        timeout: Duration,
+    ) -> Result<CompletedChannels, DdmaError> {
+        let deadline = Deadline::new(clock, timeout);
+        loop {
+            let channels = (self.reg().completion_mask()
+                | self.shared.completed.load(Ordering::Acquire))
+                & mask;
+            if channels != 0 {
+                return Ok(CompletedChannels { channels });
+            }
+            if deadline.expired() {
+                trace!(
+                    "No channel of mask {:#04x} completed within {:?}",
+                    mask, timeout
+                );
+                // Channels of the mask not held by a handle have no transfer
+                // to time out
+                #[cfg(feature = "stats")]
+                (0..8)
+                    .filter(|&ch| mask & (1 << ch) != 0 && self.shared.is_owned(ch))
+                    .for_each(|ch| self.record_timeout(ch));
+                return Err(DdmaError::Timeout);
+            }
+            spin_loop();
+        }
+    }
+
     /// Check DMA controller and channel status for debugging
     pub fn debug_status(&self, _channel: u8) -> (u32, u32, u32, u32) {
         let reg = unsafe { self.reg.as_ref() };
@@ -182,32 +546,70 @@ impl DDMA {
     }
 
     /// Clear transfer complete status for a channel
-    pub fn clear_transfer_complete(&mut self, channel: u8) {
+    pub fn clear_transfer_complete(&self, channel: u8) {
         if channel <= 7 {
-            let reg = unsafe { self.reg.as_mut() };
-            reg.clear_channel_complete(channel as usize);
+            self.reg().clear_channel_complete(channel as usize);
         }
     }
 
     /// Set channel interrupt mask
-    pub fn set_channel_interrupt_mask(&mut self, channel: u8, mask: bool) {
+    pub fn set_channel_interrupt_mask(&self, channel: u8, mask: bool) {
         if channel <= 7 {
-            let reg = unsafe { self.reg.as_mut() };
-            reg.set_channel_interrupt_mask(channel as usize, mask);
+            let _guard = self.shared.lock.lock();
+            self.reg()
+                .set_channel_interrupt_mask(channel as usize, mask);
         }
     }
 
     /// Get interrupt handler
     pub fn irq_handler(&self) -> IrqHandler {
-        IrqHandler { reg: self.reg }
+        IrqHandler {
+            reg: self.reg,
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Get the statistics counters of a channel
+    #[cfg(feature = "stats")]
+    pub fn channel_stats(&self, channel: u8) -> ChannelStats {
+        self.shared.stats.snapshot(channel)
+    }
+
+    /// Number of interrupts that arrived without any unmasked channel
+    /// completion pending, including those of other devices on a shared line
+    #[cfg(feature = "stats")]
+    pub fn spurious_irqs(&self) -> u32 {
+        self.shared.stats.spurious_irqs()
+    }
+
+    /// Record that a wait for the completion of a channel gave up
+    #[cfg(feature = "stats")]
+    pub(crate) fn record_timeout(&self, channel: u8) {
+        self.shared.stats.timed_out(channel);
+    }
+
+    /// Reset the statistics counters of a channel
+    #[cfg(feature = "stats")]
+    pub fn reset_channel_stats(&self, channel: u8) {
+        self.shared.stats.reset(channel);
+    }
+
+    /// Reset the statistics counters of all channels and the spurious interrupt count
+    #[cfg(feature = "stats")]
+    pub fn reset_stats(&self) {
+        self.shared.stats.reset_all();
     }
 }
 
 /// Interrupt handler for DDMA
 pub struct IrqHandler {
     reg: NonNull<reg::DdmaRegister>,
+    shared: Arc<Shared>,
 }
 
+// The handler only reads DMA_STAT and DMA_MASK_INT and acknowledges completions
+// with a write-1-to-clear, so it never races with the locked read-modify-write
+// sequences of `DDMA`.
 unsafe impl Send for IrqHandler {}
 unsafe impl Sync for IrqHandler {}
 
@@ -240,9 +642,21 @@ impl CompletedChannels {
 
 impl IrqHandler {
     /// Handle DMA interrupt
-    pub fn handle_irq(&self) -> CompletedChannels {
+    ///
+    /// Reports and acknowledges the completions of the channels whose
+    /// interrupt is unmasked. Completions of masked channels are left pending
+    /// in DMA_STAT for their owners to poll. Returns `None` if no unmasked
+    /// completion is pending: the interrupt is not from this controller (on a
+    /// shared line) or spurious, and is counted as such with the `stats`
+    /// feature.
+    ///
+    /// A transfer split into several hardware blocks raises one completion
+    /// per block, and each is reported here. `Channel::poll_complete` starts
+    /// the next block and returns `true` only for the last one.
+    pub fn handle_irq(&self) -> Option<CompletedChannels> {
         let reg = unsafe { self.reg.as_ref() };
         let status = reg.dma_stat.extract();
+        let mask = reg.dma_mask_int.extract();
 
         let mut completed = CompletedChannels::default();
         if status.is_set(DMA_STAT::CHAL0_SEL) {
@@ -269,14 +683,44 @@ impl IrqHandler {
         if status.is_set(DMA_STAT::CHAL7_SEL) {
             completed.set_channel_completed(7);
         }
-        // Clear all completed transfers
-        reg.dma_stat.set(u32::MAX);
 
-        completed
+        // The controller raises no interrupt while globally masked
+        let unmasked = if mask.is_set(reg::DMA_MASK_INT::GLOBAL_EN) {
+            0
+        } else {
+            !(mask.get() as u8)
+        };
+        completed.channels &= unmasked;
+
+        if completed.bitmask() == 0 {
+            trace!(
+                "No unmasked completion pending, DMA_STAT {:#x}, DMA_MASK_INT {:#x}",
+                status.get(),
+                mask.get()
+            );
+            #[cfg(feature = "stats")]
+            self.shared.stats.spurious_irq();
+            return None;
+        }
+
+        // Write-1-to-clear the reported completions only
+        let ack = (0..8)
+            .filter(|&ch| completed.is_channel_completed(ch))
+            .fold(0, |ack, ch| ack | 1 << (ch * 4));
+        reg.dma_stat.set(ack);
+
+        // Latch completions for channels polling their own status
+        self.shared
+            .completed
+            .fetch_or(completed.bitmask(), Ordering::AcqRel);
+
+        Some(completed)
     }
 }
 
 // Common peripheral slave IDs for DDMA
+//
+// See `soc` for the complete typed tables of each SoC.
 pub mod peripheral_ids {
     /// UART0 TX DMA request
     pub const UART0_TX: u8 = 2;
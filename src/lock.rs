@@ -0,0 +1,64 @@
+//! Interrupt-safe spinlock guarding read-modify-write of shared registers
+
+use spin::{Mutex, MutexGuard};
+
+/// Spinlock that keeps local IRQs masked while held, so it may also be taken
+/// from interrupt context without deadlocking against the interrupted owner
+pub(crate) struct IrqLock {
+    inner: Mutex<()>,
+}
+
+pub(crate) struct IrqGuard<'a> {
+    guard: Option<MutexGuard<'a, ()>>,
+    flags: usize,
+}
+
+impl Default for IrqLock {
+    fn default() -> Self {
+        Self {
+            inner: Mutex::new(()),
+        }
+    }
+}
+
+impl IrqLock {
+    pub fn lock(&self) -> IrqGuard<'_> {
+        let flags = irq_save();
+        IrqGuard {
+            guard: Some(self.inner.lock()),
+            flags,
+        }
+    }
+}
+
+impl Drop for IrqGuard<'_> {
+    fn drop(&mut self) {
+        // Release the lock before interrupts can be taken again
+        self.guard.take();
+        irq_restore(self.flags);
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+fn irq_save() -> usize {
+    let flags: usize;
+    unsafe {
+        core::arch::asm!("mrs {}, daif", "msr daifset, #2", out(reg) flags, options(nostack));
+    }
+    flags
+}
+
+#[cfg(target_arch = "aarch64")]
+fn irq_restore(flags: usize) {
+    unsafe {
+        core::arch::asm!("msr daif, {}", in(reg) flags, options(nostack));
+    }
+}
+
+#[cfg(not(target_arch = "aarch64"))]
+fn irq_save() -> usize {
+    0
+}
+
+#[cfg(not(target_arch = "aarch64"))]
+fn irq_restore(_flags: usize) {}
@@ -0,0 +1,126 @@
+//! Owner of all DDMA controller instances of a SoC
+
+use alloc::vec::Vec;
+
+use log::trace;
+
+use crate::{Channel, ChannelConfig, CompletedChannels, DDMA, DdmaError, DmaRequest, IrqHandler};
+
+struct Instance {
+    index: usize,
+    dma: DDMA,
+    irq: usize,
+}
+
+/// Manager owning every DDMA controller of a SoC
+///
+/// Channel requests for a typed peripheral are routed to the controller that
+/// has its request line, and interrupts are dispatched to the controllers
+/// wired to the interrupt number.
+#[derive(Default)]
+pub struct DdmaManager {
+    instances: Vec<Instance>,
+}
+
+impl DdmaManager {
+    /// Create an empty manager
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add controller `index` (0 for DMA0, as in `DmaRequest::controller`),
+    /// wired to interrupt `irq`
+    ///
+    /// Fails with `ControllerInUse` if the index was already added.
+    pub fn add(&mut self, index: usize, dma: DDMA, irq: usize) -> Result<(), DdmaError> {
+        if self.instances.iter().any(|i| i.index == index) {
+            return Err(DdmaError::ControllerInUse);
+        }
+        self.instances.push(Instance { index, dma, irq });
+        Ok(())
+    }
+
+    /// Number of controllers
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    /// Check if no controller was added
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+
+    /// Get a controller by index
+    pub fn controller(&self, index: usize) -> Option<&DDMA> {
+        self.instances
+            .iter()
+            .find(|i| i.index == index)
+            .map(|i| &i.dma)
+    }
+
+    /// Get a controller by index for exclusive access
+    pub fn controller_mut(&mut self, index: usize) -> Option<&mut DDMA> {
+        self.instances
+            .iter_mut()
+            .find(|i| i.index == index)
+            .map(|i| &mut i.dma)
+    }
+
+    /// Bind a free channel of the controller serving `request`
+    pub fn request_channel(
+        &self,
+        request: impl DmaRequest,
+        blk_size: usize,
+        irq: bool,
+    ) -> Option<Channel> {
+        let index = request.controller();
+        let Some(dma) = self.controller(index) else {
+            trace!(
+                "No DDMA controller {} for slave ID {}",
+                index,
+                request.slave_id()
+            );
+            return None;
+        };
+        dma.request_channel(ChannelConfig::for_request(request, blk_size, irq))
+    }
+
+    /// Get an interrupt dispatcher for all controllers
+    pub fn irq_handler(&self) -> ManagerIrqHandler {
+        ManagerIrqHandler {
+            handlers: self
+                .instances
+                .iter()
+                .map(|i| (i.index, i.irq, i.dma.irq_handler()))
+                .collect(),
+        }
+    }
+}
+
+/// Interrupt dispatcher for the controllers of a [`DdmaManager`]
+pub struct ManagerIrqHandler {
+    handlers: Vec<(usize, usize, IrqHandler)>,
+}
+
+impl ManagerIrqHandler {
+    /// Handle interrupt `irq` on every controller wired to it
+    ///
+    /// `on_complete` is called with the controller index and its completed
+    /// channels, for each controller with unmasked completions pending.
+    /// Returns `false` if there was none, so on a shared line the interrupt
+    /// belongs to another device.
+    pub fn handle_irq(
+        &self,
+        irq: usize,
+        mut on_complete: impl FnMut(usize, CompletedChannels),
+    ) -> bool {
+        let mut handled = false;
+        for (index, _, handler) in self.handlers.iter().filter(|(_, line, _)| *line == irq) {
+            if let Some(completed) = handler.handle_irq() {
+                on_complete(*index, completed);
+                handled = true;
+            }
+        }
+        handled
+    }
+}
@@ -0,0 +1,74 @@
+//! Register types hooking every MMIO access
+//!
+//! With the `mmio-trace` or `sim` feature `reg.rs` uses these in place of the
+//! tock-registers types. An access to a register window served by a
+//! [`crate::sim::Simulator`] goes to the model, any other access to the
+//! register itself, and with `mmio-trace` it is passed to [`crate::trace`].
+
+use tock_registers::{
+    RegisterLongName,
+    interfaces::{Readable, Writeable},
+    registers,
+};
+
+#[cfg(feature = "mmio-trace")]
+use crate::trace::{self, Op};
+
+/// Read-write register
+#[repr(transparent)]
+pub struct ReadWrite<T: tock_registers::UIntLike, R: RegisterLongName = ()>(
+    registers::ReadWrite<T, R>,
+);
+
+/// Read-only register
+#[repr(transparent)]
+pub struct ReadOnly<T: tock_registers::UIntLike, R: RegisterLongName = ()>(
+    registers::ReadOnly<T, R>,
+);
+
+fn read(addr: usize, hw: impl FnOnce() -> u32) -> u32 {
+    #[cfg(feature = "sim")]
+    let value = crate::sim::read(addr).unwrap_or_else(hw);
+    #[cfg(not(feature = "sim"))]
+    let value = hw();
+    #[cfg(feature = "mmio-trace")]
+    trace::record(Op::Read, addr, value);
+    value
+}
+
+fn write(addr: usize, value: u32, hw: impl FnOnce(u32)) {
+    #[cfg(feature = "mmio-trace")]
+    trace::record(Op::Write, addr, value);
+    #[cfg(feature = "sim")]
+    if crate::sim::write(addr, value) {
+        return;
+    }
+    hw(value);
+}
+
+impl<R: RegisterLongName> Readable for ReadWrite<u32, R> {
+    type T = u32;
+    type R = R;
+
+    fn get(&self) -> u32 {
+        read(self as *const Self as usize, || self.0.get())
+    }
+}
+
+impl<R: RegisterLongName> Writeable for ReadWrite<u32, R> {
+    type T = u32;
+    type R = R;
+
+    fn set(&self, value: u32) {
+        write(self as *const Self as usize, value, |v| self.0.set(v));
+    }
+}
+
+impl<R: RegisterLongName> Readable for ReadOnly<u32, R> {
+    type T = u32;
+    type R = R;
+
+    fn get(&self) -> u32 {
+        read(self as *const Self as usize, || self.0.get())
+    }
+}
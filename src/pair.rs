@@ -0,0 +1,197 @@
+//! Paired TX and RX channels for full-duplex peripherals such as SPI
+
+use core::{hint::spin_loop, time::Duration};
+
+use log::trace;
+
+use crate::{
+    Channel, ChannelConfig, DDMA, DdmaError, DmaDirection, TransferStatus,
+    time::{Clock, Deadline},
+};
+
+/// TX and RX channels of a full-duplex peripheral, started and completed
+/// together
+///
+/// A full-duplex transfer clocks one word in for each word out, so both
+/// channels move the same number of bytes. The transfer completes once both
+/// channels finished their block.
+pub struct ChannelPair {
+    tx: Channel,
+    rx: Channel,
+    tx_done: bool,
+    rx_done: bool,
+    /// A transfer was started and its completion not yet reported
+    started: bool,
+}
+
+impl DDMA {
+    /// Bind two free channels to the TX and RX request lines of a full-duplex
+    /// peripheral
+    ///
+    /// `tx` must be a memory-to-device and `rx` a device-to-memory
+    /// configuration.
+    pub fn request_pair(
+        &self,
+        tx: ChannelConfig,
+        rx: ChannelConfig,
+    ) -> Result<ChannelPair, DdmaError> {
+        if tx.direction != DmaDirection::MemoryToDevice
+            || rx.direction != DmaDirection::DeviceToMemory
+        {
+            return Err(DdmaError::InvalidDirection);
+        }
+
+        let tx = self.request_channel(tx).ok_or(DdmaError::NoChannel)?;
+        // The TX channel is released again when dropped on failure
+        let rx = self.request_channel(rx).ok_or(DdmaError::NoChannel)?;
+        Ok(ChannelPair::new(tx, rx))
+    }
+}
+
+impl ChannelPair {
+    /// Pair channels bound to the TX and RX request lines of one peripheral
+    pub fn new(tx: Channel, rx: Channel) -> Self {
+        Self {
+            tx,
+            rx,
+            tx_done: false,
+            rx_done: false,
+            started: false,
+        }
+    }
+
+    /// Stop both channels and give them back
+    pub fn release(mut self) -> (Channel, Channel) {
+        self.tx.deactive();
+        self.rx.deactive();
+        (self.tx, self.rx)
+    }
+
+    /// Channel writing to the peripheral
+    pub fn tx(&self) -> &Channel {
+        &self.tx
+    }
+
+    /// Mutable access to the TX channel, e.g. to fill its buffer
+    pub fn tx_mut(&mut self) -> &mut Channel {
+        &mut self.tx
+    }
+
+    /// Channel reading from the peripheral
+    pub fn rx(&self) -> &Channel {
+        &self.rx
+    }
+
+    /// Mutable access to the RX channel
+    pub fn rx_mut(&mut self) -> &mut Channel {
+        &mut self.rx
+    }
+
+    /// Start a transfer of `len` bytes in each direction
+    ///
+    /// RX is enabled before TX, so no word clocked in by the first TX word is
+    /// missed.
+    pub fn start(&mut self, len: usize) -> Result<(), DdmaError> {
+        if self.started {
+            return Err(DdmaError::Busy);
+        }
+
+        self.rx.prepare(len, false)?;
+        self.tx.prepare(len, false)?;
+
+        self.tx_done = false;
+        self.rx_done = false;
+        self.started = true;
+        self.rx.active();
+        self.tx.active();
+        trace!(
+            "Channel pair TX {} RX {} started, {} bytes",
+            self.tx.index(),
+            self.rx.index(),
+            len
+        );
+        Ok(())
+    }
+
+    /// Check the state of the transfer
+    ///
+    /// `Complete` is reported once, when both channels finished.
+    pub fn poll(&mut self) -> TransferStatus {
+        if !self.started {
+            return TransferStatus::Idle;
+        }
+
+        if !self.tx_done {
+            self.tx_done = self.tx.poll_complete();
+        }
+        if !self.rx_done {
+            self.rx_done = self.rx.poll_complete();
+        }
+        if self.tx_done && self.rx_done {
+            self.started = false;
+            TransferStatus::Complete
+        } else {
+            TransferStatus::InProgress
+        }
+    }
+
+    /// Check if the TX channel finished the current transfer
+    pub fn tx_done(&self) -> bool {
+        self.tx_done
+    }
+
+    /// Check if the RX channel finished the current transfer
+    pub fn rx_done(&self) -> bool {
+        self.rx_done
+    }
+
+    /// Wait for both channels to complete, measured with the generic timer
+    #[cfg(target_arch = "aarch64")]
+    pub fn wait_complete(&mut self, timeout: Duration) -> Result<(), DdmaError> {
+        self.wait_complete_with(&crate::time::GenericTimer, timeout)
+    }
+
+    /// Wait for both channels to complete, measured with `clock`
+    ///
+    /// On timeout both channels are stopped; [`ChannelPair::tx_done`] and
+    /// [`ChannelPair::rx_done`] tell which side stalled.
+    pub fn wait_complete_with(
+        &mut self,
+        clock: &impl Clock,
+        timeout: Duration,
+    ) -> Result<(), DdmaError> {
+        let deadline = Deadline::new(clock, timeout);
+        loop {
+            match self.poll() {
+                TransferStatus::Complete => return Ok(()),
+                TransferStatus::Idle => return Err(DdmaError::NoTransfer),
+                TransferStatus::InProgress => {}
+            }
+            if deadline.expired() {
+                trace!(
+                    "Channel pair TX {} RX {} timed out after {:?} (TX done: {}, RX done: {})",
+                    self.tx.index(),
+                    self.rx.index(),
+                    timeout,
+                    self.tx_done,
+                    self.rx_done
+                );
+                self.abort()?;
+                return Err(DdmaError::Timeout);
+            }
+            spin_loop();
+        }
+    }
+
+    /// Stop both channels, TX first so the peripheral stops clocking data
+    ///
+    /// Both channels are soft-reset, so no word left in their FIFOs leaks into
+    /// the next transfer. Fails with `Timeout` if a channel does not stop
+    /// within the wait budget.
+    pub fn abort(&mut self) -> Result<(), DdmaError> {
+        self.started = false;
+        let tx = self.tx.recover(false);
+        self.rx.recover(false)?;
+        tx
+    }
+}
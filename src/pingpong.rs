@@ -0,0 +1,257 @@
+//! Ping-pong double buffering for continuous peripheral-to-memory streams
+//!
+//! The channel fills one buffer while the consumer processes the other. On
+//! each block completion the DDR address is switched to the buffer that is not
+//! held by the consumer and the channel restarted, and the filled buffer is
+//! handed over. The controller has no descriptor chaining, so the peripheral
+//! FIFO has to absorb the data arriving while the channel is reprogrammed.
+
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicU8, AtomicU32, Ordering};
+
+use dma_api::DVec;
+use log::trace;
+
+use crate::{Channel, ChannelConfig, DDMA, DdmaError, DmaTransfer, reg::DdmaRegister};
+
+/// The buffer is unused
+const FREE: u8 = 0;
+/// The channel writes to the buffer
+const FILLING: u8 = 1;
+/// The buffer holds data not yet taken by the consumer
+const READY: u8 = 2;
+/// The consumer holds the buffer
+const HELD: u8 = 3;
+
+/// State shared by the producer and consumer halves
+struct PingPong {
+    buffers: [DVec<u8>; 2],
+    state: [AtomicU8; 2],
+    /// Filled blocks dropped because the consumer did not keep up
+    overruns: AtomicU32,
+}
+
+// The channel only uses the bus addresses of the buffers, and the state
+// machine gives the consumer exclusive access to a buffer while it is `HELD`.
+unsafe impl Send for PingPong {}
+unsafe impl Sync for PingPong {}
+
+impl PingPong {
+    fn transit(&self, index: usize, from: u8, to: u8) -> bool {
+        self.state[index]
+            .compare_exchange(from, to, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+    }
+}
+
+impl DDMA {
+    /// Bind a free channel for double-buffered streaming, with two buffers of
+    /// `config.blk_size` bytes
+    ///
+    /// The producer half is driven from the completion interrupt, the consumer
+    /// half takes the filled buffers.
+    pub fn request_double_buffer(
+        &self,
+        config: ChannelConfig,
+    ) -> Result<(DoubleBuffer, DoubleBufferReader), DdmaError> {
+        let mut transfer = DmaTransfer {
+            mem_addr: 0,
+            dev_addr: config.dev_addr,
+            size: config.blk_size,
+        };
+        // The block size is checked before the buffers are allocated
+        transfer.validate()?;
+
+        let alloc = || {
+            DVec::zeros(config.blk_size, 128, dma_api::Direction::Bidirectional)
+                .ok_or(DdmaError::NoMemory)
+        };
+        let buffers = [alloc()?, alloc()?];
+        transfer.mem_addr = buffers[0].bus_addr();
+
+        let mut channel = None;
+        for n in (0..DdmaRegister::MAX_CHANNELS as u8)
+            .filter(|&n| !self.reg().is_channel_bind(n as usize))
+        {
+            match self.prepare_transfer(&config.to_channel_config(n), &transfer) {
+                Ok(c) => {
+                    channel = Some(c);
+                    break;
+                }
+                // Bound by another core meanwhile, try the next one
+                Err(DdmaError::ChannelInUse) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        let channel = channel.ok_or(DdmaError::NoChannel)?;
+
+        let shared = Arc::new(PingPong {
+            buffers,
+            state: [AtomicU8::new(FREE), AtomicU8::new(FREE)],
+            overruns: AtomicU32::new(0),
+        });
+
+        Ok((
+            DoubleBuffer {
+                channel,
+                shared: shared.clone(),
+                dev_addr: config.dev_addr,
+                filling: 0,
+            },
+            DoubleBufferReader {
+                shared,
+                overruns_seen: 0,
+            },
+        ))
+    }
+}
+
+/// Producer half of a double-buffered stream, owning the channel
+pub struct DoubleBuffer {
+    channel: Channel,
+    shared: Arc<PingPong>,
+    dev_addr: u32,
+    /// Buffer the channel is writing to
+    filling: usize,
+}
+
+impl DoubleBuffer {
+    /// Start filling the first buffer
+    ///
+    /// Buffers still held by the consumer are left to it.
+    pub fn start(&mut self) -> Result<(), DdmaError> {
+        if self.channel.is_submitted() {
+            return Err(DdmaError::Busy);
+        }
+
+        let index = (0..2)
+            .find(|&i| self.shared.transit(i, FREE, FILLING))
+            .or_else(|| (0..2).find(|&i| self.shared.transit(i, READY, FILLING)))
+            .ok_or(DdmaError::Busy)?;
+        self.fill(index)
+    }
+
+    /// Handle a block completion, to be called after `IrqHandler::handle_irq`
+    /// or when polling
+    ///
+    /// Returns `true` if a filled buffer was handed to the consumer. If the
+    /// consumer still holds the other buffer, the block is refilled in place
+    /// and counted as an overrun. Fails if the channel could not be restarted
+    /// on the next buffer, which leaves it stopped.
+    pub fn on_complete(&mut self) -> Result<bool, DdmaError> {
+        if !self.channel.is_submitted() || !self.channel.poll_complete() {
+            return Ok(false);
+        }
+
+        let filled = self.filling;
+        let next = 1 - filled;
+
+        if self.shared.transit(next, FREE, FILLING) {
+            self.shared.state[filled].store(READY, Ordering::Release);
+        } else if self.shared.transit(next, READY, FILLING) {
+            // The consumer did not take the previous block, drop it
+            self.shared.overruns.fetch_add(1, Ordering::Relaxed);
+            self.shared.state[filled].store(READY, Ordering::Release);
+            trace!(
+                "Channel {} overrun, buffer {} dropped",
+                self.channel.index(),
+                next
+            );
+        } else {
+            // The consumer holds the other buffer, refill this one
+            self.shared.overruns.fetch_add(1, Ordering::Relaxed);
+            trace!(
+                "Channel {} overrun, buffer {} refilled",
+                self.channel.index(),
+                filled
+            );
+            self.fill(filled)?;
+            return Ok(false);
+        }
+
+        self.fill(next)?;
+        Ok(true)
+    }
+
+    fn fill(&mut self, index: usize) -> Result<(), DdmaError> {
+        let transfer = DmaTransfer {
+            mem_addr: self.shared.buffers[index].bus_addr(),
+            dev_addr: self.dev_addr,
+            size: self.shared.buffers[index].len(),
+        };
+        // Set first, so that `stop` releases the buffer if the channel fails
+        self.filling = index;
+        self.channel.set_transfer(&transfer)?;
+        self.channel.active();
+        Ok(())
+    }
+
+    /// Stop streaming
+    ///
+    /// The partially filled buffer is released, filled buffers stay available
+    /// to the consumer.
+    pub fn stop(&mut self) {
+        self.channel.deactive();
+        self.shared.transit(self.filling, FILLING, FREE);
+    }
+
+    /// Channel driving the stream
+    pub fn channel(&self) -> &Channel {
+        &self.channel
+    }
+}
+
+/// Consumer half of a double-buffered stream
+pub struct DoubleBufferReader {
+    shared: Arc<PingPong>,
+    overruns_seen: u32,
+}
+
+impl DoubleBufferReader {
+    /// Take the filled buffer, if any
+    ///
+    /// The buffer is returned to the channel when the guard is dropped, and
+    /// must be returned before the channel fills the other buffer to avoid
+    /// an overrun.
+    pub fn take(&mut self) -> Option<FilledBuffer<'_>> {
+        let index = (0..2).find(|&i| self.shared.transit(i, READY, HELD))?;
+        let overruns = self.shared.overruns.load(Ordering::Relaxed);
+        let overrun = overruns != self.overruns_seen;
+        self.overruns_seen = overruns;
+        Some(FilledBuffer {
+            shared: &self.shared,
+            index,
+            overrun,
+        })
+    }
+
+    /// Number of filled blocks dropped because they were not taken in time
+    pub fn overruns(&self) -> u32 {
+        self.shared.overruns.load(Ordering::Relaxed)
+    }
+}
+
+/// A filled buffer held by the consumer
+pub struct FilledBuffer<'a> {
+    shared: &'a PingPong,
+    index: usize,
+    overrun: bool,
+}
+
+impl FilledBuffer<'_> {
+    /// Data written by the channel
+    pub fn buff(&self) -> &DVec<u8> {
+        &self.shared.buffers[self.index]
+    }
+
+    /// Check if blocks were dropped since the previously taken buffer
+    pub fn overrun(&self) -> bool {
+        self.overrun
+    }
+}
+
+impl Drop for FilledBuffer<'_> {
+    fn drop(&mut self) {
+        self.shared.state[self.index].store(FREE, Ordering::Release);
+    }
+}
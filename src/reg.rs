@@ -1,9 +1,13 @@
 use tock_registers::{
     interfaces::{ReadWriteable, Readable, Writeable},
     register_bitfields, register_structs,
-    registers::{ReadOnly, ReadWrite},
 };
 
+#[cfg(any(feature = "mmio-trace", feature = "sim"))]
+use crate::mmio::{ReadOnly, ReadWrite};
+#[cfg(not(any(feature = "mmio-trace", feature = "sim")))]
+use tock_registers::registers::{ReadOnly, ReadWrite};
+
 register_bitfields! {
     u32,
 
@@ -256,9 +260,15 @@ register_structs! {
     }
 }
 
+impl DmaChannelRegisters {
+    /// Largest block the 32-bit transfer size register holds, keeping the
+    /// 4-byte size alignment
+    pub const MAX_BLOCK_SIZE: usize = 0xFFFF_FFFC;
+}
+
 register_structs! {
     /// DDMA Register Structure
-    /// Base addresses: DMA0: 0x0002_8003_000, DMA1: 0x0002_8004_000
+    /// Base addresses: DMA0: 0x2800_3000, DMA1: 0x2800_4000
     pub DdmaRegister {
         /// Global Control Register
         (0x00 => pub dma_ctl: ReadWrite<u32, DMA_CTL::Register>),
@@ -443,20 +453,101 @@ impl DdmaRegister {
     ///
     /// # Arguments
     /// * `channel` - Channel number (0-7)
-    pub fn clear_channel_complete(&mut self, channel: usize) {
+    pub fn clear_channel_complete(&self, channel: usize) {
+        // Write-1-to-clear: only write the bit of this channel, a read-modify-write
+        // would also acknowledge the completions pending on other channels
         match channel {
-            0 => self.dma_stat.modify(DMA_STAT::CHAL0_SEL::SET),
-            1 => self.dma_stat.modify(DMA_STAT::CHAL1_SEL::SET),
-            2 => self.dma_stat.modify(DMA_STAT::CHAL2_SEL::SET),
-            3 => self.dma_stat.modify(DMA_STAT::CHAL3_SEL::SET),
-            4 => self.dma_stat.modify(DMA_STAT::CHAL4_SEL::SET),
-            5 => self.dma_stat.modify(DMA_STAT::CHAL5_SEL::SET),
-            6 => self.dma_stat.modify(DMA_STAT::CHAL6_SEL::SET),
-            7 => self.dma_stat.modify(DMA_STAT::CHAL7_SEL::SET),
+            0 => self.dma_stat.write(DMA_STAT::CHAL0_SEL::SET),
+            1 => self.dma_stat.write(DMA_STAT::CHAL1_SEL::SET),
+            2 => self.dma_stat.write(DMA_STAT::CHAL2_SEL::SET),
+            3 => self.dma_stat.write(DMA_STAT::CHAL3_SEL::SET),
+            4 => self.dma_stat.write(DMA_STAT::CHAL4_SEL::SET),
+            5 => self.dma_stat.write(DMA_STAT::CHAL5_SEL::SET),
+            6 => self.dma_stat.write(DMA_STAT::CHAL6_SEL::SET),
+            7 => self.dma_stat.write(DMA_STAT::CHAL7_SEL::SET),
             _ => {}
         }
     }
 
+    /// Read the request source selection of a channel
+    ///
+    /// # Returns
+    /// * `(sel, enable)` - Request signal source and whether the selection is
+    ///   enabled, `(0, false)` for an invalid channel
+    pub fn channel_config(&self, channel: usize) -> (u32, bool) {
+        match channel {
+            0 => {
+                let config = self.dma_chal_config.extract();
+                (
+                    config.read(DMA_CHAL_CONFIG::CHAL0_SEL),
+                    config.is_set(DMA_CHAL_CONFIG::CHAL0_SEL_EN),
+                )
+            }
+            1 => {
+                let config = self.dma_chal_config.extract();
+                (
+                    config.read(DMA_CHAL_CONFIG::CHAL1_SEL),
+                    config.is_set(DMA_CHAL_CONFIG::CHAL1_SEL_EN),
+                )
+            }
+            2 => {
+                let config = self.dma_chal_config.extract();
+                (
+                    config.read(DMA_CHAL_CONFIG::CHAL2_SEL),
+                    config.is_set(DMA_CHAL_CONFIG::CHAL2_SEL_EN),
+                )
+            }
+            3 => {
+                let config = self.dma_chal_config.extract();
+                (
+                    config.read(DMA_CHAL_CONFIG::CHAL3_SEL),
+                    config.is_set(DMA_CHAL_CONFIG::CHAL3_SEL_EN),
+                )
+            }
+            4 => {
+                let config = self.dma_chal_config1.extract();
+                (
+                    config.read(DMA_CHAL_CONFIG1::CHAL4_SEL),
+                    config.is_set(DMA_CHAL_CONFIG1::CHAL4_SEL_EN),
+                )
+            }
+            5 => {
+                let config = self.dma_chal_config1.extract();
+                (
+                    config.read(DMA_CHAL_CONFIG1::CHAL5_SEL),
+                    config.is_set(DMA_CHAL_CONFIG1::CHAL5_SEL_EN),
+                )
+            }
+            6 => {
+                let config = self.dma_chal_config1.extract();
+                (
+                    config.read(DMA_CHAL_CONFIG1::CHAL6_SEL),
+                    config.is_set(DMA_CHAL_CONFIG1::CHAL6_SEL_EN),
+                )
+            }
+            7 => {
+                let config = self.dma_chal_config1.extract();
+                (
+                    config.read(DMA_CHAL_CONFIG1::CHAL7_SEL),
+                    config.is_set(DMA_CHAL_CONFIG1::CHAL7_SEL_EN),
+                )
+            }
+            _ => (0, false),
+        }
+    }
+
+    /// Check if the interrupt of a channel is masked
+    pub fn is_channel_interrupt_masked(&self, channel: usize) -> bool {
+        channel < Self::MAX_CHANNELS && self.dma_mask_int.get() & (1 << channel) != 0
+    }
+
+    /// Bitmask of the channels whose transfer complete flag is set
+    pub fn completion_mask(&self) -> u8 {
+        (0..Self::MAX_CHANNELS)
+            .filter(|&ch| self.is_channel_complete(ch))
+            .fold(0, |mask, ch| mask | (1 << ch))
+    }
+
     pub fn is_channel_bind(&self, channel: usize) -> bool {
         if channel >= Self::MAX_CHANNELS {
             return false;
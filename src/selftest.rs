@@ -0,0 +1,241 @@
+//! Controller self-test for board bring-up and manufacturing checks
+
+use core::mem::offset_of;
+
+use log::{debug, warn};
+use tock_registers::interfaces::{ReadWriteable, Readable, Writeable};
+
+use crate::{
+    DDMA, DdmaError,
+    reg::{DMA_CHALX_CTL, DMA_CTL, DdmaRegister, DmaChannelRegisters},
+};
+
+/// Register bits defined in DMA_MASK_INT (channel masks and global enable)
+const MASK_INT_DEFINED: u32 = 0x8000_00FF;
+
+/// Pattern written to channel registers before a channel soft reset
+const CHANNEL_PATTERN: u32 = 0x5A5A_5A58;
+
+/// A register that did not read back the expected value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterMismatch {
+    /// Offset of the register from the controller base
+    pub offset: usize,
+    /// Expected value
+    pub expected: u32,
+    /// Value read back
+    pub actual: u32,
+}
+
+/// Outcome of a single self-test check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckResult {
+    /// The check passed
+    Passed,
+    /// The check failed on the first mismatching register
+    Failed(RegisterMismatch),
+}
+
+impl CheckResult {
+    /// Check if the check passed
+    pub fn is_passed(&self) -> bool {
+        matches!(self, CheckResult::Passed)
+    }
+}
+
+/// Report produced by [`DDMA::self_test`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelfTestReport {
+    /// Number of channels reported by DMA_GCAP
+    pub channel_count: u32,
+    /// Global registers read back their hardware reset values after a
+    /// controller soft reset
+    pub reset_values: CheckResult,
+    /// DMA_GCAP reports between 1 and 8 channels
+    pub gcap: CheckResult,
+    /// Channel bind bits can be set and cleared
+    pub bind_toggle: CheckResult,
+    /// Channel interrupt mask bits can be set and cleared
+    pub mask_toggle: CheckResult,
+    /// Channel soft reset clears the channel registers
+    pub channel_reset: CheckResult,
+}
+
+impl SelfTestReport {
+    /// Check if every check passed
+    pub fn passed(&self) -> bool {
+        self.reset_values.is_passed()
+            && self.gcap.is_passed()
+            && self.bind_toggle.is_passed()
+            && self.mask_toggle.is_passed()
+            && self.channel_reset.is_passed()
+    }
+}
+
+fn check(offset: usize, expected: u32, actual: u32, mask: u32) -> CheckResult {
+    if actual & mask == expected & mask {
+        CheckResult::Passed
+    } else {
+        CheckResult::Failed(RegisterMismatch {
+            offset,
+            expected: expected & mask,
+            actual: actual & mask,
+        })
+    }
+}
+
+fn first_failure(results: impl IntoIterator<Item = CheckResult>) -> CheckResult {
+    results
+        .into_iter()
+        .find(|r| !r.is_passed())
+        .unwrap_or(CheckResult::Passed)
+}
+
+fn channel_offset(n: usize, field: usize) -> usize {
+    DdmaRegister::CHANNEL_BASE_OFFSET + n * DdmaRegister::CHANNEL_REGISTER_SIZE + field
+}
+
+impl DDMA {
+    /// Verify that the controller responds sanely after reset
+    ///
+    /// The test resets the controller, so it fails with `DdmaError::Busy`
+    /// while a `Channel` handle of this controller exists. It holds the
+    /// controller lock throughout and leaves the controller in the reset
+    /// state.
+    pub fn self_test(&self) -> Result<SelfTestReport, DdmaError> {
+        let _guard = self.shared.lock.lock();
+        if self.shared.any_owned() {
+            return Err(DdmaError::Busy);
+        }
+        let reg = self.reg();
+
+        // Soft reset only: `reset` programs the registers after it
+        reg.dma_ctl.write(DMA_CTL::DMA_ENABLE::CLEAR);
+        reg.dma_ctl.write(DMA_CTL::DMA_SRST::SET);
+        reg.dma_ctl.write(DMA_CTL::DMA_SRST::CLEAR);
+
+        // Every global register resets to zero, interrupts included
+        let reset_values = first_failure([
+            check(
+                offset_of!(DdmaRegister, dma_ctl),
+                0,
+                reg.dma_ctl.get(),
+                u32::MAX,
+            ),
+            check(
+                offset_of!(DdmaRegister, dma_stat),
+                0,
+                reg.dma_stat.get(),
+                u32::MAX,
+            ),
+            check(
+                offset_of!(DdmaRegister, dma_chal_config),
+                0,
+                reg.dma_chal_config.get(),
+                u32::MAX,
+            ),
+            check(
+                offset_of!(DdmaRegister, dma_chal_config1),
+                0,
+                reg.dma_chal_config1.get(),
+                u32::MAX,
+            ),
+            check(
+                offset_of!(DdmaRegister, dma_channel_bind),
+                0,
+                reg.dma_channel_bind.get(),
+                0xFF,
+            ),
+            check(
+                offset_of!(DdmaRegister, dma_mask_int),
+                0,
+                reg.dma_mask_int.get(),
+                MASK_INT_DEFINED,
+            ),
+        ]);
+
+        self.reset_locked();
+        let reg = self.reg();
+
+        let channel_count = reg.dma_gcap.get();
+        let gcap = if (1..=DdmaRegister::MAX_CHANNELS as u32).contains(&channel_count) {
+            CheckResult::Passed
+        } else {
+            CheckResult::Failed(RegisterMismatch {
+                offset: offset_of!(DdmaRegister, dma_gcap),
+                expected: DdmaRegister::MAX_CHANNELS as u32,
+                actual: channel_count,
+            })
+        };
+        let channels = (channel_count as usize).clamp(1, DdmaRegister::MAX_CHANNELS);
+
+        let bind_offset = offset_of!(DdmaRegister, dma_channel_bind);
+        let bind_toggle = first_failure((0..channels).flat_map(|n| {
+            reg.set_channel_bind(n, true);
+            let set = check(bind_offset, 1 << n, reg.dma_channel_bind.get(), 0xFF);
+            reg.set_channel_bind(n, false);
+            let cleared = check(bind_offset, 0, reg.dma_channel_bind.get(), 0xFF);
+            [set, cleared]
+        }));
+
+        let mask_offset = offset_of!(DdmaRegister, dma_mask_int);
+        let mask_toggle = first_failure((0..channels).flat_map(|n| {
+            reg.set_channel_interrupt_mask(n, false);
+            let unmasked = check(
+                mask_offset,
+                !(1 << n),
+                reg.dma_mask_int.get(),
+                MASK_INT_DEFINED,
+            );
+            reg.set_channel_interrupt_mask(n, true);
+            let masked = check(
+                mask_offset,
+                u32::MAX,
+                reg.dma_mask_int.get(),
+                MASK_INT_DEFINED,
+            );
+            [unmasked, masked]
+        }));
+
+        let channel_reset = first_failure((0..channels).flat_map(|n| {
+            let creg = unsafe { self.channel_reg(n as u8).as_ref() };
+            creg.ddr_lwaddr.set(CHANNEL_PATTERN);
+            creg.dev_addr.set(CHANNEL_PATTERN);
+            creg.ts.set(CHANNEL_PATTERN);
+
+            creg.ctl.modify(DMA_CHALX_CTL::CHALX_SRST::SET);
+            creg.ctl.modify(DMA_CHALX_CTL::CHALX_SRST::CLEAR);
+
+            [
+                (
+                    offset_of!(DmaChannelRegisters, ddr_lwaddr),
+                    creg.ddr_lwaddr.get(),
+                ),
+                (
+                    offset_of!(DmaChannelRegisters, dev_addr),
+                    creg.dev_addr.get(),
+                ),
+                (offset_of!(DmaChannelRegisters, ts), creg.ts.get()),
+            ]
+            .map(|(field, actual)| check(channel_offset(n, field), 0, actual, u32::MAX))
+        }));
+
+        let report = SelfTestReport {
+            channel_count,
+            reset_values,
+            gcap,
+            bind_toggle,
+            mask_toggle,
+            channel_reset,
+        };
+
+        self.reset_locked();
+
+        if report.passed() {
+            debug!("DDMA self-test passed: {:?}", report);
+        } else {
+            warn!("DDMA self-test failed: {:?}", report);
+        }
+        Ok(report)
+    }
+}
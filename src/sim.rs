@@ -0,0 +1,636 @@
+//! Behavioral model of the DDMA controller with simulated memory and
+//! peripherals, to run driver flows on a host
+//!
+//! A [`Simulator`] provides a register window to pass to `DDMA::new`. Register
+//! accesses to the window are served by the model: channels enabled while the
+//! controller is enabled move 32-bit words through their FIFO, between
+//! simulated memory and the peripheral on their request line. Each step, a
+//! channel moves at most one word on the memory side, advancing its current
+//! address, and one word on the peripheral side while the peripheral asserts
+//! its DMA request. A block is finished once all its words reached memory
+//! (RX) or the peripheral (TX); it then sets its DMA_STAT bit and, unless
+//! masked, the interrupt line.
+//!
+//! The STS register reports the FIFO occupancy: FIFO_EMPTY without buffered
+//! words, FIFO_FULL with [`FIFO_DEPTH`] of them. A soft reset of the channel
+//! or the controller empties the FIFO.
+//!
+//! The model steps once on every register read, so polling loops of the
+//! driver make progress without a separate thread; [`Simulator::step`]
+//! advances it explicitly. The simulator is also a [`Clock`] counting steps,
+//! so driver timeouts expire after a deterministic number of accesses.
+//!
+//! Host memory, such as the `DVec` buffers of the driver, is reachable by the
+//! channels once registered with [`map_host`], typically from the `dma-api`
+//! mapping of the test. Its bus address is its host address.
+//!
+//! Hardware faults can be injected with [`Simulator::inject`] and
+//! [`Simulator::raise_status`] to exercise the error paths of the driver.
+
+use alloc::{boxed::Box, collections::VecDeque, sync::Arc, vec::Vec};
+use core::{ptr::NonNull, time::Duration};
+
+use spin::Mutex;
+
+use crate::{reg::DdmaRegister, time::Clock};
+
+/// Size of the simulated register window, up to the end of the channel 7 block
+pub const WINDOW_SIZE: usize = DdmaRegister::CHANNEL_BASE_OFFSET
+    + DdmaRegister::MAX_CHANNELS * DdmaRegister::CHANNEL_REGISTER_SIZE;
+
+const DMA_CTL: usize = 0x00;
+const DMA_STAT: usize = 0x08;
+const DMA_MASK_INT: usize = 0x0C;
+const DMA_CHANNEL_BIND: usize = 0x20;
+const DMA_GCAP: usize = 0x24;
+const DMA_CHAL_CONFIG: usize = 0x04;
+const DMA_CHAL_CONFIG1: usize = 0x28;
+
+const CHAL_DDR_UPADDR: usize = 0x00;
+const CHAL_DDR_LWADDR: usize = 0x04;
+const CHAL_DEV_ADDR: usize = 0x08;
+const CHAL_TS: usize = 0x0C;
+const CHAL_CRT_UPADDR: usize = 0x10;
+const CHAL_CRT_LWADDR: usize = 0x14;
+const CHAL_CTL: usize = 0x18;
+const CHAL_STS: usize = 0x1C;
+const CHAL_TIMEOUT_CNT: usize = 0x20;
+
+/// Channel registers cleared by CHALX_SRST, all but CTL and STS
+const CHANNEL_RESET: [usize; 7] = [
+    CHAL_DDR_UPADDR,
+    CHAL_DDR_LWADDR,
+    CHAL_DEV_ADDR,
+    CHAL_TS,
+    CHAL_CRT_UPADDR,
+    CHAL_CRT_LWADDR,
+    CHAL_TIMEOUT_CNT,
+];
+
+const DMA_ENABLE: u32 = 1 << 0;
+const DMA_SRST: u32 = 1 << 1;
+const CHALX_EN: u32 = 1 << 0;
+const CHALX_SRST: u32 = 1 << 1;
+const CHALX_MODE_RX: u32 = 1 << 2;
+const FIFO_FULL: u32 = 1 << 0;
+const FIFO_EMPTY: u32 = 1 << 1;
+const GLOBAL_MASK: u32 = 1 << 31;
+
+/// Depth of the channel FIFOs, in 32-bit words
+///
+/// The depth is not documented, the value only has to be small enough for the
+/// FIFO to fill up in tests.
+pub const FIFO_DEPTH: usize = 8;
+
+/// First bus address handed out by [`Simulator::alloc`]
+const MEMORY_BASE: u64 = 0x8000_0000;
+
+/// Hardware faults, as bitmasks of channels
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Faults {
+    /// CHALX_EN can be set but no longer cleared, the channel keeps running
+    pub stuck_enable: u8,
+    /// The channel FIFO is stuck: STS reports FIFO_FULL and no data is moved,
+    /// so the transfer never finishes
+    pub stuck_fifo: u8,
+    /// Finished blocks do not set their DMA_STAT bit
+    pub lost_completion: u8,
+}
+
+/// A peripheral attached to DMA request lines
+pub trait Peripheral: Send {
+    /// `dma_tx_req`: the peripheral accepts a word from memory
+    fn tx_request(&self) -> bool;
+
+    /// `dma_rx_req`: the peripheral has a word for memory
+    fn rx_request(&self) -> bool;
+
+    /// Word written by a memory-to-device channel
+    fn write(&mut self, word: u32);
+
+    /// Word read by a device-to-memory channel
+    fn read(&mut self) -> u32;
+
+    /// Advance the peripheral by one step
+    fn tick(&mut self) {}
+}
+
+struct Attached {
+    tx_line: u8,
+    rx_line: u8,
+    peripheral: Box<dyn Peripheral>,
+}
+
+struct Region {
+    base: u64,
+    data: Vec<u8>,
+}
+
+struct Model {
+    regs: [u32; WINDOW_SIZE / 4],
+    /// Channels running a block
+    running: [bool; DdmaRegister::MAX_CHANNELS],
+    /// Words buffered in the channel FIFOs
+    fifo: [VecDeque<u32>; DdmaRegister::MAX_CHANNELS],
+    /// Words of the current block taken from the peripheral, RX only
+    received: [u64; DdmaRegister::MAX_CHANNELS],
+    memory: Vec<Region>,
+    next_addr: u64,
+    peripherals: Vec<Attached>,
+    /// Accesses to simulated memory outside any region
+    bus_errors: u32,
+    faults: Faults,
+    steps: u64,
+}
+
+impl Model {
+    fn new() -> Self {
+        let mut model = Self {
+            regs: [0; WINDOW_SIZE / 4],
+            running: [false; DdmaRegister::MAX_CHANNELS],
+            fifo: Default::default(),
+            received: [0; DdmaRegister::MAX_CHANNELS],
+            memory: Vec::new(),
+            next_addr: MEMORY_BASE,
+            peripherals: Vec::new(),
+            bus_errors: 0,
+            faults: Faults::default(),
+            steps: 0,
+        };
+        model.reset();
+        model
+    }
+
+    fn reset(&mut self) {
+        self.regs = [0; WINDOW_SIZE / 4];
+        self.regs[DMA_GCAP / 4] = DdmaRegister::MAX_CHANNELS as u32;
+        self.running = [false; DdmaRegister::MAX_CHANNELS];
+        self.fifo = Default::default();
+    }
+
+    fn reg(&self, offset: usize) -> u32 {
+        self.regs[offset / 4]
+    }
+
+    fn set_reg(&mut self, offset: usize, value: u32) {
+        self.regs[offset / 4] = value;
+    }
+
+    fn chan_offset(n: usize, reg: usize) -> usize {
+        DdmaRegister::CHANNEL_BASE_OFFSET + n * DdmaRegister::CHANNEL_REGISTER_SIZE + reg
+    }
+
+    fn chan_reg(&self, n: usize, reg: usize) -> u32 {
+        self.reg(Self::chan_offset(n, reg))
+    }
+
+    fn set_chan_reg(&mut self, n: usize, reg: usize, value: u32) {
+        self.set_reg(Self::chan_offset(n, reg), value);
+    }
+
+    fn chan_addr(&self, n: usize, up: usize) -> u64 {
+        ((self.chan_reg(n, up) as u64) << 32) | self.chan_reg(n, up + 4) as u64
+    }
+
+    fn read(&mut self, offset: usize) -> u32 {
+        self.step();
+        if let Some((n, CHAL_STS)) = split_channel(offset) {
+            return match self.fifo[n].len() {
+                _ if self.faults.stuck_fifo & (1 << n) != 0 => FIFO_FULL,
+                0 => FIFO_EMPTY,
+                FIFO_DEPTH => FIFO_FULL,
+                _ => 0,
+            };
+        }
+        self.reg(offset)
+    }
+
+    fn write(&mut self, offset: usize, value: u32) {
+        match offset {
+            DMA_CTL if value & DMA_SRST != 0 => self.reset(),
+            DMA_STAT => self.set_reg(offset, self.reg(offset) & !value),
+            DMA_GCAP => {}
+            _ => match split_channel(offset) {
+                Some((n, CHAL_CTL)) => self.write_chan_ctl(n, value),
+                // Current addresses are advanced by the controller only
+                Some((_, CHAL_CRT_UPADDR | CHAL_CRT_LWADDR | CHAL_STS)) => {}
+                _ => self.set_reg(offset, value),
+            },
+        }
+    }
+
+    fn write_chan_ctl(&mut self, n: usize, mut value: u32) {
+        let was_enabled = self.chan_reg(n, CHAL_CTL) & CHALX_EN != 0;
+        if was_enabled && self.faults.stuck_enable & (1 << n) != 0 {
+            value |= CHALX_EN;
+        }
+        self.set_chan_reg(n, CHAL_CTL, value);
+
+        if value & CHALX_SRST != 0 {
+            // Channel related registers and FIFO are reset
+            for reg in CHANNEL_RESET {
+                self.set_chan_reg(n, reg, 0);
+            }
+            self.running[n] = false;
+            self.fifo[n].clear();
+        }
+        if value & CHALX_EN == 0 {
+            self.running[n] = false;
+        } else if !was_enabled {
+            // A block starts from the DDR address on the enable edge
+            self.set_chan_reg(n, CHAL_CRT_UPADDR, self.chan_reg(n, CHAL_DDR_UPADDR));
+            self.set_chan_reg(n, CHAL_CRT_LWADDR, self.chan_reg(n, CHAL_DDR_LWADDR));
+            self.running[n] = true;
+            self.received[n] = 0;
+        }
+    }
+
+    /// Request line selected for channel `n`, if enabled
+    fn request_line(&self, n: usize) -> Option<u8> {
+        let config = if n < 4 {
+            self.reg(DMA_CHAL_CONFIG)
+        } else {
+            self.reg(DMA_CHAL_CONFIG1)
+        };
+        let sel = (config >> ((n % 4) * 8)) & 0xFF;
+        (sel & 0x80 != 0).then_some((sel & 0x7F) as u8)
+    }
+
+    fn step(&mut self) {
+        self.steps += 1;
+        if self.reg(DMA_CTL) & DMA_ENABLE != 0 {
+            for n in 0..DdmaRegister::MAX_CHANNELS {
+                self.step_channel(n);
+            }
+        }
+        for p in &mut self.peripherals {
+            p.peripheral.tick();
+        }
+    }
+
+    fn step_channel(&mut self, n: usize) {
+        let bound = self.reg(DMA_CHANNEL_BIND) & (1 << n) != 0;
+        if !self.running[n] || !bound || self.faults.stuck_fifo & (1 << n) != 0 {
+            return;
+        }
+        let Some(line) = self.request_line(n) else {
+            return;
+        };
+
+        let rx = self.chan_reg(n, CHAL_CTL) & CHALX_MODE_RX != 0;
+        let ddr = self.chan_addr(n, CHAL_DDR_UPADDR);
+        let mut crt = self.chan_addr(n, CHAL_CRT_UPADDR);
+        let words = self.chan_reg(n, CHAL_TS) as u64 / 4;
+        // DDR may be reprogrammed above CRT while the channel runs
+        let moved = |crt: u64| crt.saturating_sub(ddr) / 4;
+
+        if rx {
+            // Memory side first: a word takes at least a step through the FIFO
+            if let Some(word) = self.fifo[n].pop_front() {
+                self.write_mem(crt, &word.to_le_bytes());
+                crt += 4;
+            }
+            if self.received[n] < words
+                && self.fifo[n].len() < FIFO_DEPTH
+                && let Some(p) = self.peripheral(line, true)
+                && p.rx_request()
+            {
+                let word = p.read();
+                self.fifo[n].push_back(word);
+                self.received[n] += 1;
+            }
+        } else {
+            if let Some(&word) = self.fifo[n].front()
+                && let Some(p) = self.peripheral(line, false)
+                && p.tx_request()
+            {
+                p.write(word);
+                self.fifo[n].pop_front();
+            }
+            if moved(crt) < words && self.fifo[n].len() < FIFO_DEPTH {
+                let mut word = [0; 4];
+                self.read_mem(crt, &mut word);
+                self.fifo[n].push_back(u32::from_le_bytes(word));
+                crt += 4;
+            }
+        }
+        self.set_chan_reg(n, CHAL_CRT_UPADDR, (crt >> 32) as u32);
+        self.set_chan_reg(n, CHAL_CRT_LWADDR, crt as u32);
+
+        if moved(crt) < words || !self.fifo[n].is_empty() {
+            return;
+        }
+        self.running[n] = false;
+        if self.faults.lost_completion & (1 << n) == 0 {
+            self.set_reg(DMA_STAT, self.reg(DMA_STAT) | 1 << (n * 4));
+        }
+    }
+
+    /// Peripheral on the RX or TX request line `line`
+    fn peripheral(&mut self, line: u8, rx: bool) -> Option<&mut Box<dyn Peripheral>> {
+        self.peripherals
+            .iter_mut()
+            .find(|p| {
+                if rx {
+                    p.rx_line == line
+                } else {
+                    p.tx_line == line
+                }
+            })
+            .map(|p| &mut p.peripheral)
+    }
+
+    fn region(&mut self, addr: u64, len: usize) -> Option<&mut [u8]> {
+        let region = self
+            .memory
+            .iter_mut()
+            .find(|r| addr >= r.base && addr + len as u64 <= r.base + r.data.len() as u64)?;
+        let start = (addr - region.base) as usize;
+        Some(&mut region.data[start..start + len])
+    }
+
+    fn read_mem(&mut self, addr: u64, buf: &mut [u8]) {
+        if let Some(data) = self.region(addr, buf.len()) {
+            buf.copy_from_slice(data);
+        } else if let Some(ptr) = host(addr, buf.len()) {
+            // SAFETY: registered with `map_host` as valid for the range
+            unsafe { core::ptr::copy(ptr, buf.as_mut_ptr(), buf.len()) };
+        } else {
+            buf.fill(0);
+            self.bus_errors += 1;
+        }
+    }
+
+    fn write_mem(&mut self, addr: u64, buf: &[u8]) {
+        if let Some(data) = self.region(addr, buf.len()) {
+            data.copy_from_slice(buf);
+        } else if let Some(ptr) = host(addr, buf.len()) {
+            // SAFETY: registered with `map_host` as valid for the range
+            unsafe { core::ptr::copy(buf.as_ptr(), ptr, buf.len()) };
+        } else {
+            self.bus_errors += 1;
+        }
+    }
+
+    fn irq_asserted(&self) -> bool {
+        let stat = self.reg(DMA_STAT);
+        let mask = self.reg(DMA_MASK_INT);
+        mask & GLOBAL_MASK == 0
+            && (0..DdmaRegister::MAX_CHANNELS)
+                .any(|n| stat & (1 << (n * 4)) != 0 && mask & (1 << n) == 0)
+    }
+}
+
+fn split_channel(offset: usize) -> Option<(usize, usize)> {
+    let offset = offset.checked_sub(DdmaRegister::CHANNEL_BASE_OFFSET)?;
+    Some((
+        offset / DdmaRegister::CHANNEL_REGISTER_SIZE,
+        offset % DdmaRegister::CHANNEL_REGISTER_SIZE,
+    ))
+}
+
+/// Host memory ranges reachable by the channels, as (address, size)
+static HOST_MEMORY: Mutex<Vec<(usize, usize)>> = Mutex::new(Vec::new());
+
+/// Make `size` bytes of host memory at `addr` reachable by the channels of
+/// every simulator, returning their bus address
+///
+/// # Safety
+///
+/// The memory must stay valid for reads and writes until [`unmap_host`], and
+/// is accessed by the model without synchronization, as by a real DMA master.
+pub unsafe fn map_host(addr: NonNull<u8>, size: usize) -> u64 {
+    HOST_MEMORY.lock().push((addr.as_ptr() as usize, size));
+    addr.as_ptr() as u64
+}
+
+/// Make host memory registered with [`map_host`] unreachable again
+pub fn unmap_host(addr: NonNull<u8>) {
+    let addr = addr.as_ptr() as usize;
+    HOST_MEMORY.lock().retain(|&(a, _)| a != addr);
+}
+
+/// Host pointer to the `len` bytes at bus address `addr`, if mapped
+fn host(addr: u64, len: usize) -> Option<*mut u8> {
+    let addr = usize::try_from(addr).ok()?;
+    let end = addr.checked_add(len)?;
+    HOST_MEMORY
+        .lock()
+        .iter()
+        .any(|&(a, size)| addr >= a && end <= a + size)
+        .then_some(addr as *mut u8)
+}
+
+/// Register windows served by simulators
+static SIMULATORS: Mutex<Vec<(usize, Arc<Mutex<Model>>)>> = Mutex::new(Vec::new());
+
+fn find(addr: usize) -> Option<(usize, Arc<Mutex<Model>>)> {
+    SIMULATORS
+        .lock()
+        .iter()
+        .find(|(base, _)| (*base..*base + WINDOW_SIZE).contains(&addr))
+        .map(|(base, model)| (addr - base, model.clone()))
+}
+
+pub(crate) fn read(addr: usize) -> Option<u32> {
+    let (offset, model) = find(addr)?;
+    let value = model.lock().read(offset);
+    Some(value)
+}
+
+pub(crate) fn write(addr: usize, value: u32) -> bool {
+    let Some((offset, model)) = find(addr) else {
+        return false;
+    };
+    model.lock().write(offset, value);
+    true
+}
+
+/// Simulated DDMA controller
+pub struct Simulator {
+    model: Arc<Mutex<Model>>,
+    /// Backing of the register window, only used for its addresses
+    window: Box<[u32; WINDOW_SIZE / 4]>,
+}
+
+impl Default for Simulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Simulator {
+    /// Create a controller in its reset state
+    pub fn new() -> Self {
+        let window = Box::new([0; WINDOW_SIZE / 4]);
+        let model = Arc::new(Mutex::new(Model::new()));
+        SIMULATORS
+            .lock()
+            .push((window.as_ptr() as usize, model.clone()));
+        Self { model, window }
+    }
+
+    /// Base address of the register window, for `DDMA::new`
+    pub fn base(&self) -> NonNull<u8> {
+        NonNull::from(&*self.window).cast()
+    }
+
+    /// Allocate `len` bytes of zeroed simulated memory, returning their bus
+    /// address
+    pub fn alloc(&self, len: usize) -> u64 {
+        let mut model = self.model.lock();
+        let base = model.next_addr;
+        model.next_addr = (base + len as u64).next_multiple_of(0x40);
+        model.memory.push(Region {
+            base,
+            data: alloc::vec![0; len],
+        });
+        base
+    }
+
+    /// Write simulated memory
+    pub fn write_mem(&self, addr: u64, data: &[u8]) {
+        self.model.lock().write_mem(addr, data);
+    }
+
+    /// Read simulated memory
+    pub fn read_mem(&self, addr: u64, len: usize) -> Vec<u8> {
+        let mut buf = alloc::vec![0; len];
+        self.model.lock().read_mem(addr, &mut buf);
+        buf
+    }
+
+    /// Attach a peripheral to the TX and RX request lines `tx_line` and
+    /// `rx_line`
+    pub fn attach(&self, tx_line: u8, rx_line: u8, peripheral: impl Peripheral + 'static) {
+        self.model.lock().peripherals.push(Attached {
+            tx_line,
+            rx_line,
+            peripheral: Box::new(peripheral),
+        });
+    }
+
+    /// Advance the controller and the peripherals by one step
+    pub fn step(&self) {
+        self.model.lock().step();
+    }
+
+    /// State of the interrupt line
+    pub fn irq_asserted(&self) -> bool {
+        self.model.lock().irq_asserted()
+    }
+
+    /// Inject hardware faults, replacing the previous ones
+    pub fn inject(&self, faults: Faults) {
+        self.model.lock().faults = faults;
+    }
+
+    /// Set DMA_STAT bits without a finished block, as a spurious completion
+    pub fn raise_status(&self, bits: u32) {
+        let mut model = self.model.lock();
+        let stat = model.reg(DMA_STAT);
+        model.set_reg(DMA_STAT, stat | bits);
+    }
+
+    /// Number of channel accesses to unallocated simulated memory
+    pub fn bus_errors(&self) -> u32 {
+        self.model.lock().bus_errors
+    }
+}
+
+/// Simulated time, one microsecond per step
+impl Clock for Simulator {
+    fn now(&self) -> Duration {
+        Duration::from_micros(self.model.lock().steps)
+    }
+}
+
+impl Drop for Simulator {
+    fn drop(&mut self) {
+        let base = self.window.as_ptr() as usize;
+        SIMULATORS.lock().retain(|(b, _)| *b != base);
+    }
+}
+
+struct UartState {
+    fifo_depth: usize,
+    tx_fifo: VecDeque<u8>,
+    output: Vec<u8>,
+    input: VecDeque<u8>,
+    loopback: bool,
+}
+
+/// UART with a TX FIFO drained one character per step and an RX FIFO fed
+/// by the test
+///
+/// As with the PL011, each DMA word carries one character in bits 7:0. The
+/// TX request is asserted while the TX FIFO has room, the RX request while
+/// received characters are pending. Clones share the same UART.
+#[derive(Clone)]
+pub struct SimUart {
+    state: Arc<Mutex<UartState>>,
+}
+
+impl SimUart {
+    /// Create a UART with a `fifo_depth`-character TX FIFO
+    pub fn new(fifo_depth: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(UartState {
+                fifo_depth,
+                tx_fifo: VecDeque::new(),
+                output: Vec::new(),
+                input: VecDeque::new(),
+                loopback: false,
+            })),
+        }
+    }
+
+    /// Characters transmitted on the line so far
+    pub fn output(&self) -> Vec<u8> {
+        self.state.lock().output.clone()
+    }
+
+    /// Characters still waiting in the TX FIFO
+    pub fn tx_pending(&self) -> usize {
+        self.state.lock().tx_fifo.len()
+    }
+
+    /// Receive characters from the line
+    pub fn push_input(&self, data: &[u8]) {
+        self.state.lock().input.extend(data);
+    }
+
+    /// Also receive every transmitted character, as with UARTCR.LBE set
+    pub fn set_loopback(&self, loopback: bool) {
+        self.state.lock().loopback = loopback;
+    }
+}
+
+impl Peripheral for SimUart {
+    fn tx_request(&self) -> bool {
+        let state = self.state.lock();
+        state.tx_fifo.len() < state.fifo_depth
+    }
+
+    fn rx_request(&self) -> bool {
+        !self.state.lock().input.is_empty()
+    }
+
+    fn write(&mut self, word: u32) {
+        self.state.lock().tx_fifo.push_back(word as u8);
+    }
+
+    fn read(&mut self) -> u32 {
+        self.state.lock().input.pop_front().unwrap_or_default() as u32
+    }
+
+    fn tick(&mut self) {
+        let mut state = self.state.lock();
+        if let Some(c) = state.tx_fifo.pop_front() {
+            state.output.push(c);
+            if state.loopback {
+                state.input.push_back(c);
+            }
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! Per-SoC tables of DDMA peripheral request lines
+//!
+//! Each SoC module is enabled by the cargo feature of the same name and
+//! provides a `SlaveId` enum implementing [`DmaRequest`], so a channel can be
+//! configured from a typed peripheral instead of raw numbers. A line missing
+//! from the tables is described with [`RawRequest`].
+
+use crate::{DdmaError, DmaDirection};
+
+/// A peripheral DMA request line of a SoC
+pub trait DmaRequest: Copy {
+    /// Request signal source number programmed into DMA_CHAL_CONFIG
+    fn slave_id(self) -> u8;
+
+    /// Transfer direction served by the request line
+    fn direction(self) -> DmaDirection;
+
+    /// Bus address of the peripheral FIFO
+    fn fifo_addr(self) -> u32;
+
+    /// Index of the controller owning the request line (0 for DMA0)
+    fn controller(self) -> usize {
+        0
+    }
+}
+
+/// A request line given by its raw numbers, for peripherals missing from the
+/// SoC tables
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawRequest {
+    slave_id: u8,
+    direction: DmaDirection,
+    fifo_addr: u32,
+    controller: usize,
+}
+
+impl RawRequest {
+    /// Request line `slave_id` of DDMA0, moving data in `direction` to or from
+    /// the peripheral FIFO at bus address `fifo_addr`
+    ///
+    /// Fails with `InvalidSlaveId` if `slave_id` does not fit DMA_CHAL_CONFIG.
+    pub fn new(slave_id: u8, direction: DmaDirection, fifo_addr: u32) -> Result<Self, DdmaError> {
+        if slave_id > 31 {
+            return Err(DdmaError::InvalidSlaveId);
+        }
+        Ok(Self {
+            slave_id,
+            direction,
+            fifo_addr,
+            controller: 0,
+        })
+    }
+
+    /// The same request line on controller `controller`
+    pub fn on_controller(self, controller: usize) -> Self {
+        Self { controller, ..self }
+    }
+}
+
+impl DmaRequest for RawRequest {
+    fn slave_id(self) -> u8 {
+        self.slave_id
+    }
+
+    fn direction(self) -> DmaDirection {
+        self.direction
+    }
+
+    fn fifo_addr(self) -> u32 {
+        self.fifo_addr
+    }
+
+    fn controller(self) -> usize {
+        self.controller
+    }
+}
+
+/// Phytium E2000 family (E2000D/Q/S) request lines
+///
+/// Base addresses and request numbers follow `soc/pe220x/fparameters_comm.h`
+/// of the Phytium standalone SDK (`FDDMA*_BASE_ADDR`, `FUART*_BASE_ADDR`,
+/// `FSPIM*_BASE_ADDR` and the `FDDMA0_*_SLAVE_ID` macros). Only DDMA0 lines
+/// are listed, describe DDMA1 lines with
+/// [`RawRequest::on_controller`](super::RawRequest::on_controller).
+#[cfg(feature = "e2000")]
+pub mod e2000 {
+    use super::DmaRequest;
+    use crate::DmaDirection;
+
+    /// DDMA0 base address
+    pub const DDMA0_BASE: usize = 0x2800_3000;
+    /// DDMA1 base address
+    pub const DDMA1_BASE: usize = 0x2800_4000;
+
+    /// UART0 base address
+    pub const UART0_BASE: u32 = 0x2800_C000;
+    /// UART1 base address
+    pub const UART1_BASE: u32 = 0x2800_D000;
+    /// UART2 base address
+    pub const UART2_BASE: u32 = 0x2800_E000;
+    /// UART3 base address
+    pub const UART3_BASE: u32 = 0x2800_F000;
+    /// SPI master 0 base address
+    pub const SPIM0_BASE: u32 = 0x2801_4000;
+    /// SPI master 1 base address
+    pub const SPIM1_BASE: u32 = 0x2801_5000;
+    /// SPI master 2 base address
+    pub const SPIM2_BASE: u32 = 0x2801_6000;
+    /// SPI master 3 base address
+    pub const SPIM3_BASE: u32 = 0x2801_7000;
+
+    /// Offset of the PL011 data register (UARTDR)
+    pub const UART_FIFO_OFFSET: u32 = 0x00;
+    /// Offset of the SPI master data register (DR)
+    pub const SPIM_FIFO_OFFSET: u32 = 0x60;
+
+    /// Request lines of DDMA0
+    #[repr(u8)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum SlaveId {
+        Uart0Tx = 2,
+        Uart1Tx = 3,
+        Uart2Tx = 4,
+        Uart3Tx = 5,
+        Spim0Tx = 6,
+        Spim1Tx = 7,
+        Spim2Tx = 8,
+        Spim3Tx = 9,
+        Uart0Rx = 15,
+        Uart1Rx = 16,
+        Uart2Rx = 17,
+        Uart3Rx = 18,
+        Spim0Rx = 19,
+        Spim1Rx = 20,
+        Spim2Rx = 21,
+        Spim3Rx = 22,
+    }
+
+    impl DmaRequest for SlaveId {
+        fn slave_id(self) -> u8 {
+            self as u8
+        }
+
+        fn direction(self) -> DmaDirection {
+            match self {
+                SlaveId::Uart0Tx
+                | SlaveId::Uart1Tx
+                | SlaveId::Uart2Tx
+                | SlaveId::Uart3Tx
+                | SlaveId::Spim0Tx
+                | SlaveId::Spim1Tx
+                | SlaveId::Spim2Tx
+                | SlaveId::Spim3Tx => DmaDirection::MemoryToDevice,
+                _ => DmaDirection::DeviceToMemory,
+            }
+        }
+
+        fn fifo_addr(self) -> u32 {
+            match self {
+                SlaveId::Uart0Tx | SlaveId::Uart0Rx => UART0_BASE + UART_FIFO_OFFSET,
+                SlaveId::Uart1Tx | SlaveId::Uart1Rx => UART1_BASE + UART_FIFO_OFFSET,
+                SlaveId::Uart2Tx | SlaveId::Uart2Rx => UART2_BASE + UART_FIFO_OFFSET,
+                SlaveId::Uart3Tx | SlaveId::Uart3Rx => UART3_BASE + UART_FIFO_OFFSET,
+                SlaveId::Spim0Tx | SlaveId::Spim0Rx => SPIM0_BASE + SPIM_FIFO_OFFSET,
+                SlaveId::Spim1Tx | SlaveId::Spim1Rx => SPIM1_BASE + SPIM_FIFO_OFFSET,
+                SlaveId::Spim2Tx | SlaveId::Spim2Rx => SPIM2_BASE + SPIM_FIFO_OFFSET,
+                SlaveId::Spim3Tx | SlaveId::Spim3Rx => SPIM3_BASE + SPIM_FIFO_OFFSET,
+            }
+        }
+    }
+}
+
+/// Phytium Pi, built on the E2000Q: the SDK builds its board support
+/// (`board/phytiumpi`) on the same `soc/pe220x` parameters
+#[cfg(feature = "phytium-pi")]
+pub mod phytium_pi {
+    pub use super::e2000::*;
+}
@@ -0,0 +1,132 @@
+//! Per-channel transfer statistics, enabled with the `stats` feature.
+
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+
+use crate::reg::DdmaRegister;
+
+/// Snapshot of the counters of one channel
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChannelStats {
+    /// Number of transfers submitted, a split transfer counting once
+    pub transfers: u32,
+    /// Number of bytes submitted
+    pub bytes: u64,
+    /// Number of submitted transfers that completed
+    pub completions: u32,
+    /// Number of waits that gave up before the transfer completed
+    pub timeouts: u32,
+    /// Number of transfers stopped before they completed
+    pub aborts: u32,
+    /// Number of completions consumed while no transfer was in flight
+    pub spurious: u32,
+}
+
+#[derive(Default)]
+struct Counters {
+    transfers: AtomicU32,
+    bytes: AtomicU64,
+    completions: AtomicU32,
+    timeouts: AtomicU32,
+    aborts: AtomicU32,
+    spurious: AtomicU32,
+    in_flight: AtomicBool,
+}
+
+impl Counters {
+    fn snapshot(&self) -> ChannelStats {
+        ChannelStats {
+            transfers: self.transfers.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            completions: self.completions.load(Ordering::Relaxed),
+            timeouts: self.timeouts.load(Ordering::Relaxed),
+            aborts: self.aborts.load(Ordering::Relaxed),
+            spurious: self.spurious.load(Ordering::Relaxed),
+        }
+    }
+
+    fn reset(&self) {
+        self.transfers.store(0, Ordering::Relaxed);
+        self.bytes.store(0, Ordering::Relaxed);
+        self.completions.store(0, Ordering::Relaxed);
+        self.timeouts.store(0, Ordering::Relaxed);
+        self.aborts.store(0, Ordering::Relaxed);
+        self.spurious.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Counters shared between `DDMA`, its channels and its `IrqHandler`
+#[derive(Default)]
+pub(crate) struct Stats {
+    channels: [Counters; DdmaRegister::MAX_CHANNELS],
+    spurious_irqs: AtomicU32,
+}
+
+impl Stats {
+    fn channel(&self, channel: u8) -> Option<&Counters> {
+        self.channels.get(channel as usize)
+    }
+
+    /// A transfer of `bytes` bytes was started on `channel`
+    pub fn submitted(&self, channel: u8, bytes: usize) {
+        if let Some(c) = self.channel(channel) {
+            c.transfers.fetch_add(1, Ordering::Relaxed);
+            c.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+            c.in_flight.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// The last block of the transfer on `channel` completed
+    pub fn completed(&self, channel: u8) {
+        if let Some(c) = self.channel(channel) {
+            if c.in_flight.swap(false, Ordering::Relaxed) {
+                c.completions.fetch_add(1, Ordering::Relaxed);
+            } else {
+                c.spurious.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// A wait on `channel` gave up
+    pub fn timed_out(&self, channel: u8) {
+        if let Some(c) = self.channel(channel) {
+            c.timeouts.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// `channel` was disabled, which aborts a transfer still in flight
+    pub fn deactivated(&self, channel: u8) {
+        if let Some(c) = self.channel(channel)
+            && c.in_flight.swap(false, Ordering::Relaxed)
+        {
+            c.aborts.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// An interrupt arrived without any unmasked completion pending
+    pub fn spurious_irq(&self) {
+        self.spurious_irqs.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self, channel: u8) -> ChannelStats {
+        self.channel(channel)
+            .map(Counters::snapshot)
+            .unwrap_or_default()
+    }
+
+    pub fn spurious_irqs(&self) -> u32 {
+        self.spurious_irqs.load(Ordering::Relaxed)
+    }
+
+    pub fn reset(&self, channel: u8) {
+        if let Some(c) = self.channel(channel) {
+            c.reset();
+        }
+    }
+
+    pub fn reset_all(&self) {
+        for c in &self.channels {
+            c.reset();
+        }
+        self.spurious_irqs.store(0, Ordering::Relaxed);
+    }
+}
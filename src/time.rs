@@ -0,0 +1,89 @@
+//! Time base used to bound waits on the hardware
+
+use core::{
+    hint::spin_loop,
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+
+/// Monotonic time source
+pub trait Clock {
+    /// Time elapsed since an arbitrary fixed origin
+    fn now(&self) -> Duration;
+}
+
+/// AArch64 generic timer, read from CNTPCT_EL0 and CNTFRQ_EL0
+#[cfg(target_arch = "aarch64")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GenericTimer;
+
+#[cfg(target_arch = "aarch64")]
+impl Clock for GenericTimer {
+    fn now(&self) -> Duration {
+        use aarch64_cpu_ext::{
+            asm::barrier,
+            registers::{CNTFRQ_EL0, CNTPCT_EL0, Readable},
+        };
+
+        barrier::isb(barrier::SY);
+        let cnt = CNTPCT_EL0.get();
+        let freq = CNTFRQ_EL0.get();
+        if freq == 0 {
+            return Duration::ZERO;
+        }
+        let nanos = cnt as u128 * 1_000_000_000 / freq as u128;
+        Duration::from_nanos(nanos as u64)
+    }
+}
+
+/// Deadline measured with a [`Clock`]
+pub(crate) struct Deadline<'a, C: Clock> {
+    clock: &'a C,
+    start: Duration,
+    timeout: Duration,
+}
+
+impl<'a, C: Clock> Deadline<'a, C> {
+    pub fn new(clock: &'a C, timeout: Duration) -> Self {
+        Self {
+            clock,
+            start: clock.now(),
+            timeout,
+        }
+    }
+
+    pub fn expired(&self) -> bool {
+        self.clock.now().saturating_sub(self.start) > self.timeout
+    }
+}
+
+/// Number of polls a busy-wait on the hardware may take, set with
+/// `DDMA::set_wait_budget`
+pub(crate) struct WaitBudget(AtomicU32);
+
+impl Default for WaitBudget {
+    fn default() -> Self {
+        Self(AtomicU32::new(crate::DDMA::DEFAULT_WAIT_BUDGET))
+    }
+}
+
+impl WaitBudget {
+    pub fn get(&self) -> u32 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn set(&self, polls: u32) {
+        self.0.store(polls, Ordering::Relaxed);
+    }
+}
+
+/// Call `poll` until it returns a value, at most `polls` times (at least once)
+pub(crate) fn spin_wait<T>(polls: u32, mut poll: impl FnMut() -> Option<T>) -> Option<T> {
+    for _ in 0..polls.max(1) {
+        if let Some(value) = poll() {
+            return Some(value);
+        }
+        spin_loop();
+    }
+    None
+}
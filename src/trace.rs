@@ -0,0 +1,215 @@
+//! Recording of register accesses, to check the access sequence of the
+//! driver against the SDK
+//!
+//! A recording covers the register window of one controller, from
+//! [`start`] to [`stop`]. The resulting [`Trace`] prints as one access per
+//! line, `R`/`W`, offset and value:
+//!
+//! ```text
+//! W 0x000 0x00000000
+//! R 0x020 0x00000001
+//! ```
+//!
+//! and parses back from that format, so golden sequences can be kept as
+//! text files and compared with [`Trace::compare`].
+
+use alloc::vec::Vec;
+use core::{fmt, ptr::NonNull};
+
+use spin::Mutex;
+
+/// Kind of register access
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Read,
+    Write,
+}
+
+/// A register access
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Access {
+    pub op: Op,
+    /// Offset from the controller base
+    pub offset: usize,
+    /// Value read or written
+    pub value: u32,
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self.op {
+            Op::Read => 'R',
+            Op::Write => 'W',
+        };
+        write!(f, "{} 0x{:03x} 0x{:08x}", op, self.offset, self.value)
+    }
+}
+
+/// Recorded sequence of register accesses
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+    pub accesses: Vec<Access>,
+}
+
+/// Error parsing a trace
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: expected `R|W OFFSET VALUE`", self.line)
+    }
+}
+
+/// First difference between a trace and its golden sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mismatch {
+    /// Index of the access
+    pub index: usize,
+    /// Access of the golden sequence, `None` if the trace is longer
+    pub expected: Option<Access>,
+    /// Recorded access, `None` if the trace is shorter
+    pub actual: Option<Access>,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "access {}: expected ", self.index)?;
+        match &self.expected {
+            Some(a) => write!(f, "`{a}`")?,
+            None => write!(f, "end of trace")?,
+        }
+        write!(f, ", got ")?;
+        match &self.actual {
+            Some(a) => write!(f, "`{a}`"),
+            None => write!(f, "end of trace"),
+        }
+    }
+}
+
+impl Trace {
+    /// Parse the text format, skipping empty lines and `#` comments
+    pub fn parse(text: &str) -> Result<Self, ParseError> {
+        let mut accesses = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let err = ParseError { line: i + 1 };
+            let mut tokens = line.split_whitespace();
+            let op = match tokens.next() {
+                Some("R") => Op::Read,
+                Some("W") => Op::Write,
+                _ => return Err(err),
+            };
+            let mut hex = || {
+                tokens
+                    .next()
+                    .and_then(|t| t.strip_prefix("0x"))
+                    .and_then(|t| u32::from_str_radix(t, 16).ok())
+                    .ok_or(err)
+            };
+            let offset = hex()? as usize;
+            let value = hex()?;
+            accesses.push(Access { op, offset, value });
+        }
+        Ok(Self { accesses })
+    }
+
+    /// Only the writes of the trace
+    pub fn writes(&self) -> Self {
+        Self {
+            accesses: self
+                .accesses
+                .iter()
+                .filter(|a| a.op == Op::Write)
+                .copied()
+                .collect(),
+        }
+    }
+
+    /// Compare with a golden sequence
+    ///
+    /// Operations and offsets must match in order. Written values must match,
+    /// read values are ignored since they depend on the hardware state.
+    pub fn compare(&self, golden: &Trace) -> Result<(), Mismatch> {
+        let len = self.accesses.len().max(golden.accesses.len());
+        for index in 0..len {
+            let expected = golden.accesses.get(index).copied();
+            let actual = self.accesses.get(index).copied();
+            let same = match (expected, actual) {
+                (Some(e), Some(a)) => {
+                    e.op == a.op && e.offset == a.offset && (e.op == Op::Read || e.value == a.value)
+                }
+                _ => false,
+            };
+            if !same {
+                return Err(Mismatch {
+                    index,
+                    expected,
+                    actual,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for access in &self.accesses {
+            writeln!(f, "{access}")?;
+        }
+        Ok(())
+    }
+}
+
+struct Recording {
+    base: usize,
+    size: usize,
+    trace: Trace,
+}
+
+/// Active recordings, one per register window
+static RECORDINGS: Mutex<Vec<Recording>> = Mutex::new(Vec::new());
+
+/// Start recording the accesses to the `size`-byte register window at `base`
+///
+/// A recording already running for the window is restarted.
+pub fn start(base: NonNull<u8>, size: usize) {
+    let base = base.as_ptr() as usize;
+    let mut recordings = RECORDINGS.lock();
+    recordings.retain(|r| r.base != base);
+    recordings.push(Recording {
+        base,
+        size,
+        trace: Trace::default(),
+    });
+}
+
+/// Stop recording the window at `base` and return its trace
+pub fn stop(base: NonNull<u8>) -> Trace {
+    let base = base.as_ptr() as usize;
+    let mut recordings = RECORDINGS.lock();
+    match recordings.iter().position(|r| r.base == base) {
+        Some(i) => recordings.swap_remove(i).trace,
+        None => Trace::default(),
+    }
+}
+
+pub(crate) fn record(op: Op, addr: usize, value: u32) {
+    let mut recordings = RECORDINGS.lock();
+    if let Some(r) = recordings
+        .iter_mut()
+        .find(|r| (r.base..r.base + r.size).contains(&addr))
+    {
+        r.trace.accesses.push(Access {
+            op,
+            offset: addr - r.base,
+            value,
+        });
+    }
+}
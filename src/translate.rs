@@ -0,0 +1,32 @@
+//! Translation of addresses programmed into the controller
+//!
+//! Without a translation the controller is given DMA memory addresses as
+//! reported by the allocator and device addresses as physical addresses. A
+//! translation is needed when the controller sits behind an SMMU, or in a
+//! guest whose physical addresses differ from the bus addresses.
+
+use crate::DdmaError;
+
+/// Mapping from the addresses known to the driver to bus addresses
+///
+/// Both methods default to the identity.
+pub trait AddressTranslation: Send + Sync {
+    /// Bus address of DMA memory, from `DVec::bus_addr` or a caller-provided
+    /// `DmaTransfer::mem_addr`
+    fn mem_addr(&self, addr: u64) -> u64 {
+        addr
+    }
+
+    /// Bus address of a peripheral register, from its physical address
+    fn dev_addr(&self, addr: u64) -> u64 {
+        addr
+    }
+}
+
+/// Check that a translated device address fits the 32-bit DEV_ADDR register
+pub(crate) fn check_dev_addr(addr: u64) -> Result<u32, DdmaError> {
+    u32::try_from(addr).map_err(|_| {
+        log::trace!("Device address 0x{:x} does not fit in 32 bits", addr);
+        DdmaError::InvalidAddress
+    })
+}
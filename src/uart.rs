@@ -0,0 +1,265 @@
+//! `embedded-io` adapter for a PL011 UART driven by DDMA channels
+//!
+//! The controller moves 32-bit words to and from the UART data register, and
+//! the PL011 takes one character per word (UARTDR bits 7:0), so each byte
+//! occupies one word of the channel buffers.
+
+use core::hint::spin_loop;
+
+use log::trace;
+
+use crate::{Channel, ChannelConfig, DDMA, DdmaError, DmaRequest, time::spin_wait};
+
+/// Bytes used in a channel buffer for each character
+const WORD: usize = 4;
+
+/// UART with DMA transmit and receive
+///
+/// Writes are chunked into blocks of the TX channel buffer. Reception runs
+/// continuously into the RX channel buffer as a cyclic transfer, and reads
+/// return the characters received since the previous read. If the transfer
+/// overwrote characters before they were read, a read fails once with
+/// `DdmaError::Overrun` and the following reads return the newer characters.
+///
+/// Blocking writes and flushes wait for the TX channel at most the wait
+/// budget of the controller (`DDMA::set_wait_budget`) and then fail with
+/// `DdmaError::Timeout`. Blocking reads wait until a character is received,
+/// as `embedded_io::Read` requires, use [`DmaUart::read_with_budget`] to give
+/// up on a quiet line.
+///
+/// The `async` implementations busy-poll: each future wakes itself on every
+/// poll, keeping the executor running at full CPU load until the transfer is
+/// done.
+pub struct DmaUart {
+    tx: Channel,
+    rx: Channel,
+    /// Next RX word to hand out
+    rx_read: usize,
+    /// The RX block completed and was restarted, but its tail is not read yet
+    rx_full: bool,
+}
+
+impl DmaUart {
+    /// Create the adapter from channels bound to the UART TX and RX request
+    /// lines with the data register as device address, and start reception
+    ///
+    /// The controller must be enabled for data to flow.
+    pub fn new(tx: Channel, mut rx: Channel) -> Result<Self, DdmaError> {
+        if tx.try_buff().is_none() || rx.try_buff().is_none() {
+            return Err(DdmaError::NoBuffer);
+        }
+
+        let rx_len = rx.capacity() - rx.capacity() % WORD;
+        rx.prepare(rx_len, true)?;
+        rx.active();
+
+        Ok(Self {
+            tx,
+            rx,
+            rx_read: 0,
+            rx_full: false,
+        })
+    }
+
+    /// Bind two free channels of `dma` to the UART request lines, with
+    /// buffers holding `chars` characters each
+    pub fn request(
+        dma: &DDMA,
+        tx: impl DmaRequest,
+        rx: impl DmaRequest,
+        chars: usize,
+    ) -> Result<Self, DdmaError> {
+        let tx = dma
+            .request_channel(ChannelConfig::for_request(tx, chars * WORD, false))
+            .ok_or(DdmaError::NoChannel)?;
+        let rx = dma
+            .request_channel(ChannelConfig::for_request(rx, chars * WORD, false))
+            .ok_or(DdmaError::NoChannel)?;
+        Self::new(tx, rx)
+    }
+
+    /// Stop both channels and give them back
+    pub fn release(mut self) -> (Channel, Channel) {
+        self.tx.deactive();
+        self.rx.deactive();
+        (self.tx, self.rx)
+    }
+
+    /// Read like `embedded_io::Read::read`, but fail with
+    /// `DdmaError::Timeout` after `polls` checks without a received character
+    pub fn read_with_budget(&mut self, buf: &mut [u8], polls: u32) -> Result<usize, DdmaError> {
+        spin_wait(polls, || self.try_read(buf).transpose()).unwrap_or(Err(DdmaError::Timeout))
+    }
+
+    fn tx_idle(&mut self) -> bool {
+        !self.tx.is_submitted() || self.tx.poll_complete()
+    }
+
+    /// Start transmitting a chunk of `buf` if the previous one is done
+    fn try_write(&mut self, buf: &[u8]) -> Result<Option<usize>, DdmaError> {
+        if buf.is_empty() {
+            return Ok(Some(0));
+        }
+        if !self.tx_idle() {
+            return Ok(None);
+        }
+
+        let n = buf.len().min(self.tx.capacity() / WORD);
+        let buff = self.tx.buff_mut();
+        for (i, &b) in buf[..n].iter().enumerate() {
+            // Upper bytes of the word stay zero
+            buff.set(i * WORD, b);
+        }
+        self.tx.prepare(n * WORD, false)?;
+        self.tx.active();
+        Ok(Some(n))
+    }
+
+    /// Copy received characters into `buf`, if any
+    fn try_read(&mut self, buf: &mut [u8]) -> Result<Option<usize>, DdmaError> {
+        if buf.is_empty() {
+            return Ok(Some(0));
+        }
+
+        let words = self.rx.capacity() / WORD;
+        if self.rx.poll_complete() {
+            if self.rx_read == words {
+                // The block was read before its completion was seen
+                self.rx_read = 0;
+            } else if self.rx_full {
+                // A whole block completed over the unread tail of the previous
+                // one, keep the new block
+                trace!("UART RX overrun, {} characters lost", words - self.rx_read);
+                self.rx_read = 0;
+                return Err(DdmaError::Overrun);
+            } else {
+                self.rx_full = true;
+            }
+        }
+        let written = self.rx.transferred()? / WORD;
+        if self.rx_full && written > self.rx_read {
+            // The restarted block overwrote the start of the unread tail
+            trace!("UART RX overrun, {} characters lost", words - self.rx_read);
+            self.rx_full = false;
+            self.rx_read = 0;
+            return Err(DdmaError::Overrun);
+        }
+        let end = if self.rx_full { words } else { written };
+        if end <= self.rx_read {
+            return Ok(None);
+        }
+
+        let n = buf.len().min(end - self.rx_read);
+        let buff = self.rx.buff();
+        for (i, b) in buf[..n].iter_mut().enumerate() {
+            *b = buff.get((self.rx_read + i) * WORD).unwrap_or_default();
+        }
+        self.rx_read += n;
+        if self.rx_full && self.rx_read == words {
+            self.rx_full = false;
+            self.rx_read = 0;
+        }
+        Ok(Some(n))
+    }
+}
+
+impl embedded_io::Error for DdmaError {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        match self {
+            DdmaError::Timeout => embedded_io::ErrorKind::TimedOut,
+            DdmaError::InvalidSize => embedded_io::ErrorKind::InvalidInput,
+            _ => embedded_io::ErrorKind::Other,
+        }
+    }
+}
+
+impl embedded_io::ErrorType for DmaUart {
+    type Error = DdmaError;
+}
+
+impl embedded_io::Write for DmaUart {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, DdmaError> {
+        let polls = self.tx.wait_budget();
+        spin_wait(polls, || self.try_write(buf).transpose()).unwrap_or(Err(DdmaError::Timeout))
+    }
+
+    fn flush(&mut self) -> Result<(), DdmaError> {
+        let polls = self.tx.wait_budget();
+        spin_wait(polls, || self.tx_idle().then_some(())).ok_or(DdmaError::Timeout)
+    }
+}
+
+impl embedded_io::Read for DmaUart {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, DdmaError> {
+        loop {
+            if let Some(n) = self.try_read(buf)? {
+                return Ok(n);
+            }
+            spin_loop();
+        }
+    }
+}
+
+/// The controller only interrupts on block completion, so the async adapter
+/// polls cooperatively, yielding to the executor between checks. The futures
+/// wake themselves on every poll and never let the executor idle.
+#[cfg(feature = "async")]
+mod asynch {
+    use core::{
+        future::Future,
+        pin::Pin,
+        task::{Context, Poll},
+    };
+
+    use super::DmaUart;
+    use crate::DdmaError;
+
+    struct YieldNow(bool);
+
+    impl Future for YieldNow {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 {
+                Poll::Ready(())
+            } else {
+                self.0 = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    fn yield_now() -> YieldNow {
+        YieldNow(false)
+    }
+
+    impl embedded_io_async::Write for DmaUart {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, DdmaError> {
+            loop {
+                if let Some(n) = self.try_write(buf)? {
+                    return Ok(n);
+                }
+                yield_now().await;
+            }
+        }
+
+        async fn flush(&mut self) -> Result<(), DdmaError> {
+            while !self.tx_idle() {
+                yield_now().await;
+            }
+            Ok(())
+        }
+    }
+
+    impl embedded_io_async::Read for DmaUart {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, DdmaError> {
+            loop {
+                if let Some(n) = self.try_read(buf)? {
+                    return Ok(n);
+                }
+                yield_now().await;
+            }
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! Detection of channels that stop making progress
+//!
+//! A channel can stall without raising its completion, for example when its
+//! FIFO wedges. The watchdog is checked periodically on each running channel
+//! and samples its current address and FIFO status: a channel whose current
+//! address and FIFO do not change for longer than the threshold is reported
+//! as stalled, and aborted or restarted if configured so.
+
+use core::time::Duration;
+
+use log::warn;
+
+use crate::{Channel, DdmaError, DmaDirection, FifoStatus, reg::DdmaRegister, time::Clock};
+
+/// What the watchdog does with a stalled channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StallAction {
+    /// Only report the stall
+    Report,
+    /// Stop and reset the channel, see [`Channel::recover`]
+    Abort,
+    /// Restart the current block with [`Channel::retry`], or stop the
+    /// channel once its retry policy is exhausted
+    Restart,
+}
+
+/// A stall detected by the watchdog
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stall {
+    /// Bytes moved by the transfer when it stalled
+    pub transferred: usize,
+    /// FIFO state when the stall was detected
+    pub fifo: FifoStatus,
+    /// Time since the last progress
+    pub duration: Duration,
+    /// Action taken on the channel, `Abort` for a restart refused by the
+    /// retry policy
+    pub action: StallAction,
+}
+
+/// Result of a watchdog check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchdogStatus {
+    /// No transfer is running on the channel
+    Idle,
+    /// The channel made progress within the threshold, or is waiting for its
+    /// peripheral
+    Running,
+    /// The channel made no progress for longer than the threshold
+    Stalled(Stall),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    transferred: usize,
+    fifo: FifoStatus,
+    /// Time of the last progress
+    since: Duration,
+}
+
+/// Watchdog of the channels of a controller
+pub struct Watchdog {
+    threshold: Duration,
+    action: StallAction,
+    samples: [Option<Sample>; DdmaRegister::MAX_CHANNELS],
+}
+
+impl Watchdog {
+    /// Create a watchdog reporting channels stalled for more than `threshold`,
+    /// and taking `action` on them
+    pub fn new(threshold: Duration, action: StallAction) -> Self {
+        Self {
+            threshold,
+            action,
+            samples: [None; DdmaRegister::MAX_CHANNELS],
+        }
+    }
+
+    /// Sample `channel` and check it for a stall, measured with `clock`
+    ///
+    /// To be called periodically, at least a few times per threshold, on
+    /// each channel to watch. An RX channel with an empty FIFO is waiting for
+    /// its peripheral to send data and is not considered stalled, unless its
+    /// whole block was moved without a completion.
+    ///
+    /// Fails if the progress of the channel cannot be read or if the recovery
+    /// of a stalled channel fails.
+    pub fn check(
+        &mut self,
+        channel: &mut Channel,
+        clock: &impl Clock,
+    ) -> Result<WatchdogStatus, DdmaError> {
+        let n = channel.index() as usize;
+        if !channel.is_submitted() || !channel.is_running() {
+            self.samples[n] = None;
+            return Ok(WatchdogStatus::Idle);
+        }
+
+        let now = clock.now();
+        let transferred = channel.transferred()?;
+        let fifo = channel.fifo_status();
+        // Nothing to move until the peripheral sends data, unless the block is
+        // done and its completion missing
+        let waiting = channel.direction() == DmaDirection::DeviceToMemory
+            && fifo.empty
+            && !channel.block_moved()?;
+
+        let sample = match self.samples[n] {
+            Some(s) if s.transferred == transferred && s.fifo == fifo && !waiting => s,
+            _ => Sample {
+                transferred,
+                fifo,
+                since: now,
+            },
+        };
+        self.samples[n] = Some(sample);
+
+        let duration = now.saturating_sub(sample.since);
+        if duration <= self.threshold {
+            return Ok(WatchdogStatus::Running);
+        }
+
+        warn!(
+            "Channel {} stalled for {:?} after {} bytes, FIFO {:?}",
+            n, duration, transferred, fifo
+        );
+        let action = match self.action {
+            StallAction::Report => StallAction::Report,
+            StallAction::Abort => {
+                channel.recover(false)?;
+                StallAction::Abort
+            }
+            StallAction::Restart if channel.retry()? => StallAction::Restart,
+            StallAction::Restart => StallAction::Abort,
+        };
+        if action != StallAction::Report {
+            self.samples[n] = None;
+        }
+
+        Ok(WatchdogStatus::Stalled(Stall {
+            transferred,
+            fifo,
+            duration,
+            action,
+        }))
+    }
+
+    /// Forget the samples of channel `n`, e.g. after it was reprogrammed
+    pub fn forget(&mut self, n: u8) {
+        if let Some(sample) = self.samples.get_mut(n as usize) {
+            *sample = None;
+        }
+    }
+}
@@ -0,0 +1,42 @@
+//! Fixtures shared by the host tests on the simulated controller
+
+#![allow(dead_code)]
+
+use core::ptr::NonNull;
+
+use dma_api::{Direction, Impl};
+
+#[cfg(feature = "sim")]
+mod uart;
+
+#[cfg(feature = "sim")]
+#[allow(unused_imports)]
+pub use uart::*;
+
+/// DMA mapping of the host: bus addresses are the virtual addresses, and
+/// mapped buffers are reachable by the simulated controllers
+struct HostDma;
+
+impl Impl for HostDma {
+    fn map(addr: NonNull<u8>, size: usize, _direction: Direction) -> u64 {
+        #[cfg(feature = "sim")]
+        // SAFETY: `dma-api` keeps the buffer allocated until it is unmapped
+        unsafe {
+            phytium_ddma::sim::map_host(addr, size);
+        }
+        #[cfg(not(feature = "sim"))]
+        let _ = size;
+        addr.as_ptr() as u64
+    }
+
+    fn unmap(_addr: NonNull<u8>, _size: usize) {
+        #[cfg(feature = "sim")]
+        phytium_ddma::sim::unmap_host(_addr);
+    }
+
+    fn flush(_addr: NonNull<u8>, _size: usize) {}
+
+    fn invalidate(_addr: NonNull<u8>, _size: usize) {}
+}
+
+dma_api::set_impl!(HostDma);
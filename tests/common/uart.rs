@@ -0,0 +1,96 @@
+//! A simulated controller with a UART on the UART1 request lines
+
+use phytium_ddma::{
+    Channel, DDMA, DmaChannelConfig, DmaDirection, DmaTransfer, peripheral_ids,
+    sim::{SimUart, Simulator},
+};
+
+/// UART1 data register
+pub const UART1_DR: u32 = 0x2800_D000;
+
+/// A simulated controller, reset and enabled, with a UART on the UART1
+/// request lines
+pub fn setup() -> (Simulator, SimUart, DDMA) {
+    let sim = Simulator::new();
+    let uart = SimUart::new(8);
+    sim.attach(
+        peripheral_ids::UART1_TX,
+        peripheral_ids::UART1_RX,
+        uart.clone(),
+    );
+    let dma = DDMA::new(sim.base());
+    dma.reset();
+    dma.enable();
+    (sim, uart, dma)
+}
+
+/// Binding of `channel` to the UART1 request line of `direction`
+pub fn uart_config(channel: u8, direction: DmaDirection) -> DmaChannelConfig {
+    DmaChannelConfig {
+        channel,
+        peripheral_id: match direction {
+            DmaDirection::MemoryToDevice => peripheral_ids::UART1_TX,
+            DmaDirection::DeviceToMemory => peripheral_ids::UART1_RX,
+        },
+        direction,
+        timeout_enable: false,
+        timeout_count: 0,
+        irq: true,
+    }
+}
+
+/// One character per word, in bits 7:0 of the UART data register
+pub fn words(msg: &[u8]) -> Vec<u8> {
+    msg.iter().flat_map(|&c| (c as u32).to_le_bytes()).collect()
+}
+
+/// Characters of `len` words of simulated memory at `addr`
+pub fn chars(sim: &Simulator, addr: u64, len: usize) -> Vec<u8> {
+    sim.read_mem(addr, len * 4)
+        .chunks(4)
+        .map(|word| word[0])
+        .collect()
+}
+
+/// Bind `channel` to UART1 TX, sending `msg` from simulated memory, returning
+/// the channel and the address of its simulated memory
+pub fn uart_tx(sim: &Simulator, dma: &DDMA, channel: u8, msg: &[u8]) -> (Channel, u64) {
+    let words = words(msg);
+    let mem_addr = sim.alloc(words.len());
+    sim.write_mem(mem_addr, &words);
+    let channel = dma
+        .prepare_transfer(
+            &uart_config(channel, DmaDirection::MemoryToDevice),
+            &DmaTransfer {
+                mem_addr,
+                dev_addr: UART1_DR,
+                size: words.len(),
+            },
+        )
+        .unwrap();
+    (channel, mem_addr)
+}
+
+/// Bind `channel` to UART1 RX for `len` characters, returning the channel and
+/// the address of its simulated memory
+pub fn uart_rx(sim: &Simulator, dma: &DDMA, channel: u8, len: usize) -> (Channel, u64) {
+    let mem_addr = sim.alloc(len * 4);
+    let channel = dma
+        .prepare_transfer(
+            &uart_config(channel, DmaDirection::DeviceToMemory),
+            &DmaTransfer {
+                mem_addr,
+                dev_addr: UART1_DR,
+                size: len * 4,
+            },
+        )
+        .unwrap();
+    (channel, mem_addr)
+}
+
+/// Step the simulator until the UART sent everything it holds
+pub fn drain(sim: &Simulator, uart: &SimUart) {
+    while uart.tx_pending() > 0 {
+        sim.step();
+    }
+}
@@ -0,0 +1,120 @@
+//! Parsing and comparing register dumps
+//!
+//! Runs on the host:
+//!
+//! ```bash
+//! cargo test --features decode --test decode --target x86_64-unknown-linux-gnu
+//! ```
+
+use phytium_ddma::decode::{Dump, ParseError, WINDOW_SIZE, channel_offset};
+
+/// Offset of DMA_STAT
+const DMA_STAT: usize = 0x08;
+/// Offset of the transfer size register in a channel block
+const CHAL_TS: usize = 0x0C;
+
+#[test]
+fn uboot_md_with_ascii_column() {
+    // The ASCII column of the last line reads as a hex word
+    let text = "\
+=> md.l 0x28003000 9
+28003000: 00000001 00000000 00000101 00000000    ................
+28003010: 00000000 00000000 00000000 00000000    ................
+28003020: 66656562                               beef
+";
+    // The prompt line has no address and no hex word
+    let dump = Dump::parse_hex(text).unwrap();
+    assert_eq!(dump.word(0x00), Some(1));
+    assert_eq!(dump.word(DMA_STAT), Some(0x101));
+    assert_eq!(dump.word(0x20), Some(0x6665_6562));
+    assert_eq!(dump.word(0x24), None);
+}
+
+#[test]
+fn addresses_place_words() {
+    // Lines in any order, relative to the first address
+    let text = "\
+# channel 0 only
+28003040: 00000000 80001000
+28003054: 00000010
+";
+    let dump = Dump::parse_hex(text).unwrap();
+    assert_eq!(dump.word(0x04), Some(0x8000_1000));
+    assert_eq!(dump.word(0x14), Some(0x10));
+    assert_eq!(dump.word(0x08), None);
+
+    // Plain words from offset 0, with or without prefix
+    let dump = Dump::parse_hex("0x1 0X2\n3").unwrap();
+    assert_eq!(dump.word(0x00), Some(1));
+    assert_eq!(dump.word(0x04), Some(2));
+    assert_eq!(dump.word(0x08), Some(3));
+}
+
+#[test]
+fn words_outside_the_window() {
+    // Before the first address
+    assert_eq!(
+        Dump::parse_hex("28003010: 00000001\n28003000: 00000002"),
+        Err(ParseError::OutOfWindow { line: 2 })
+    );
+
+    // Past the end of the window
+    let text = format!(
+        "28003000: 00000001\n{:08x}: 00000002",
+        0x2800_3000 + WINDOW_SIZE
+    );
+    assert_eq!(
+        Dump::parse_hex(&text),
+        Err(ParseError::OutOfWindow { line: 2 })
+    );
+    let last = format!("{:08x}: 00000002", 0x2800_3000 + WINDOW_SIZE - 4);
+    assert!(Dump::parse_hex(&format!("28003000: 00000001\n{last}")).is_ok());
+
+    assert_eq!(
+        Dump::parse_hex("2800300g: 00000001"),
+        Err(ParseError::InvalidHex { line: 1 })
+    );
+    assert_eq!(Dump::parse_hex("# nothing\n\n"), Err(ParseError::Empty));
+}
+
+#[test]
+fn binary_dump() {
+    let mut bytes: Vec<u8> = [1u32, 0, 0x101]
+        .iter()
+        .flat_map(|w| w.to_le_bytes())
+        .collect();
+    // A trailing partial word is not a register
+    bytes.extend_from_slice(&[0xFF, 0xFF]);
+
+    let dump = Dump::from_bytes(&bytes);
+    assert_eq!(dump.word(0x00), Some(1));
+    assert_eq!(dump.word(DMA_STAT), Some(0x101));
+    assert_eq!(dump.word(0x0C), None);
+
+    // The same words as text
+    assert_eq!(Dump::parse_hex("00000001 00000000 00000101").unwrap(), dump);
+}
+
+#[test]
+fn diff() {
+    let old = Dump::parse_hex("00000001 00000000 00000000").unwrap();
+    let mut bytes = vec![0u8; WINDOW_SIZE];
+    bytes[..4].copy_from_slice(&1u32.to_le_bytes());
+    bytes[DMA_STAT..DMA_STAT + 4].copy_from_slice(&0x10u32.to_le_bytes());
+    let ts = channel_offset(1) + CHAL_TS;
+    bytes[ts..ts + 4].copy_from_slice(&0x40u32.to_le_bytes());
+    let new = Dump::from_bytes(&bytes);
+
+    assert_eq!(old.diff(&old).count(), 0);
+
+    let changes: Vec<_> = old.diff(&new).collect();
+    let (stat_old, stat_new) = changes.iter().find(|(a, _)| a.offset == DMA_STAT).unwrap();
+    assert_eq!(stat_old.info.name, "DMA_STAT");
+    assert_eq!((stat_old.value, stat_new.value), (Some(0), Some(0x10)));
+
+    // Registers only the new dump covers count as changed
+    let (ts_old, ts_new) = changes.iter().find(|(a, _)| a.offset == ts).unwrap();
+    assert_eq!(ts_old.channel, Some(1));
+    assert_eq!((ts_old.value, ts_new.value), (None, Some(0x40)));
+    assert!(changes.iter().all(|(a, _)| a.offset != 0));
+}
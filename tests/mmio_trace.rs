@@ -0,0 +1,88 @@
+//! Register access sequences compared with golden traces
+//!
+//! Runs on the host against the simulated controller, so registers behave as
+//! on hardware (DMA_STAT is write-1-to-clear):
+//!
+//! ```bash
+//! cargo test --features mmio-trace,sim --test mmio_trace --target x86_64-unknown-linux-gnu
+//! ```
+//!
+//! The golden files are written by hand from the SDK driver, see the comments
+//! in each file. A mismatch prints the recorded trace for review; it must not
+//! be copied over a golden file without checking it against the SDK.
+
+mod common;
+
+use std::{fs, path::PathBuf};
+
+use phytium_ddma::{
+    DDMA, DmaChannelConfig, DmaDirection, DmaTransfer,
+    sim::{Simulator, WINDOW_SIZE},
+    trace::{self, Trace},
+};
+
+fn uart1_tx() -> (DmaChannelConfig, DmaTransfer) {
+    (
+        DmaChannelConfig {
+            channel: 0,
+            peripheral_id: 3,
+            direction: DmaDirection::MemoryToDevice,
+            timeout_enable: false,
+            timeout_count: 0,
+            irq: true,
+        },
+        DmaTransfer {
+            mem_addr: 0x8000_1000,
+            dev_addr: 0x2800_D000,
+            size: 4,
+        },
+    )
+}
+
+fn check(name: &str, actual: &Trace) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{name}.trace"));
+    let text = fs::read_to_string(&path).unwrap();
+    let golden = Trace::parse(&text).unwrap();
+    if let Err(mismatch) = actual.compare(&golden) {
+        panic!("{name}: {mismatch}\nrecorded trace:\n{actual}");
+    }
+}
+
+#[test]
+fn reset() {
+    let sim = Simulator::new();
+    let dma = DDMA::new(sim.base());
+
+    trace::start(sim.base(), WINDOW_SIZE);
+    dma.reset();
+    check("reset", &trace::stop(sim.base()));
+}
+
+#[test]
+fn bind_channel() {
+    let sim = Simulator::new();
+    let dma = DDMA::new(sim.base());
+    dma.reset();
+    let (config, transfer) = uart1_tx();
+
+    trace::start(sim.base(), WINDOW_SIZE);
+    let channel = dma.prepare_transfer(&config, &transfer).unwrap();
+    check("bind_channel", &trace::stop(sim.base()));
+    drop(channel);
+}
+
+#[test]
+fn start_transfer() {
+    let sim = Simulator::new();
+    let dma = DDMA::new(sim.base());
+    dma.reset();
+    let (config, transfer) = uart1_tx();
+    let mut channel = dma.prepare_transfer(&config, &transfer).unwrap();
+
+    trace::start(sim.base(), WINDOW_SIZE);
+    channel.clear_and_active(&dma);
+    dma.enable();
+    check("start_transfer", &trace::stop(sim.base()));
+}
@@ -0,0 +1,86 @@
+//! Saving and restoring the controller state on the simulated controller
+//!
+//! Runs on the host:
+//!
+//! ```bash
+//! cargo test --features sim --test sim_context --target x86_64-unknown-linux-gnu
+//! ```
+
+mod common;
+
+use std::time::Duration;
+
+use phytium_ddma::{DdmaContext, DdmaError};
+
+use common::{chars, drain, setup, uart_rx, uart_tx};
+
+/// Simulated time given to a transfer, far more than it needs
+const TIMEOUT: Duration = Duration::from_millis(1);
+
+#[test]
+fn save_reset_restore() {
+    let (sim, uart, dma) = setup();
+    let msg = b"done";
+
+    // Channel 0 waits for input, channel 1 finished without being polled
+    let (mut rx, mem_addr) = uart_rx(&sim, &dma, 0, 4);
+    rx.clear_and_active(&dma);
+    let (mut tx, _) = uart_tx(&sim, &dma, 1, msg);
+    tx.clear_and_active(&dma);
+    drain(&sim, &uart);
+    while !dma.is_transfer_complete(1) {
+        sim.step();
+    }
+
+    let ctx = dma.save_context();
+    assert_eq!(ctx.channel_bind & 0xFF, 0b11);
+    assert_eq!(ctx.pending, 1 << 1);
+
+    // Powered down
+    dma.reset();
+    assert_eq!(dma.debug_status(0).2, 0);
+    dma.restore_context(&ctx);
+
+    // Registers are back, except the finished block that is not restarted,
+    // and the pending completion is latched in memory
+    let mut expected = DdmaContext { pending: 0, ..ctx };
+    expected.channels[1].ctl &= !1;
+    assert_eq!(dma.save_context(), expected);
+    assert!(tx.poll_complete());
+    assert!(!tx.poll_complete());
+    assert_eq!(uart.output(), msg);
+
+    // The waiting channel carries on
+    uart.push_input(b"more");
+    rx.wait_complete_with(&sim, TIMEOUT).unwrap();
+    assert_eq!(chars(&sim, mem_addr, 4), b"more");
+}
+
+#[test]
+fn restore_resyncs_channel_ownership() {
+    let (sim, _uart, dma) = setup();
+
+    // Channel 0 is bound in the saved state
+    let (_rx, _) = uart_rx(&sim, &dma, 0, 4);
+    let ctx = dma.save_context();
+
+    // Channel 2 is bound after the save, and completes
+    let (mut tx, _) = uart_tx(&sim, &dma, 2, b"late");
+    tx.clear_and_active(&dma);
+    while !sim.irq_asserted() {
+        sim.step();
+    }
+    dma.irq_handler().handle_irq().unwrap();
+
+    dma.reset();
+    dma.restore_context(&ctx);
+
+    // Channel 2 is unbound again, its handle no longer holds it and its
+    // completion is gone
+    assert!(!tx.poll_complete());
+    assert_eq!(dma.adopt_channel(2).err(), Some(DdmaError::NotBound));
+    uart_tx(&sim, &dma, 2, b"new");
+
+    // Channel 0 is still held by its handle
+    assert_eq!(dma.adopt_channel(0).err(), Some(DdmaError::ChannelInUse));
+}
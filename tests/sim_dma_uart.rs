@@ -0,0 +1,115 @@
+//! The `embedded-io` UART adapter on the simulated controller
+//!
+//! Runs on the host:
+//!
+//! ```bash
+//! cargo test --features sim,embedded-io --test sim_dma_uart --target x86_64-unknown-linux-gnu
+//! ```
+
+mod common;
+
+use embedded_io::{Read, Write};
+use phytium_ddma::{
+    DdmaError, DmaDirection, DmaUart, RawRequest, peripheral_ids,
+    sim::{SimUart, Simulator},
+};
+
+use common::{UART1_DR, drain, setup};
+
+/// Characters held by each channel buffer
+const CHARS: usize = 4;
+
+/// Polls a blocking write may take
+const WAIT_BUDGET: u32 = 10_000;
+
+fn dma_uart() -> (Simulator, SimUart, DmaUart) {
+    let (sim, uart, dma) = setup();
+    dma.set_wait_budget(WAIT_BUDGET);
+    let tx = RawRequest::new(
+        peripheral_ids::UART1_TX,
+        DmaDirection::MemoryToDevice,
+        UART1_DR,
+    )
+    .unwrap();
+    let rx = RawRequest::new(
+        peripheral_ids::UART1_RX,
+        DmaDirection::DeviceToMemory,
+        UART1_DR,
+    )
+    .unwrap();
+    let dma_uart = DmaUart::request(&dma, tx, rx, CHARS).unwrap();
+    (sim, uart, dma_uart)
+}
+
+/// Step the simulator until the UART handed all its input to the controller
+fn receive(sim: &Simulator) {
+    for _ in 0..1000 {
+        sim.step();
+    }
+}
+
+#[test]
+fn write_flush_read() {
+    let (sim, uart, mut dma_uart) = dma_uart();
+    let msg = b"longer than one block\n";
+
+    dma_uart.write_all(msg).unwrap();
+    dma_uart.flush().unwrap();
+    drain(&sim, &uart);
+    assert_eq!(uart.output(), msg);
+
+    // Read back across the wrap of the RX block
+    let mut received = Vec::new();
+    for chunk in [&b"ab"[..], b"cdef", b"g"] {
+        uart.push_input(chunk);
+        let mut buf = [0; 8];
+        let mut n = 0;
+        while n < chunk.len() {
+            n += dma_uart.read(&mut buf[n..]).unwrap();
+        }
+        received.extend_from_slice(&buf[..n]);
+    }
+    assert_eq!(received, b"abcdefg");
+}
+
+#[test]
+fn overrun_is_reported_once() {
+    let (sim, uart, mut dma_uart) = dma_uart();
+    let mut buf = [0; CHARS];
+
+    // A full block, of which only one character is read
+    uart.push_input(b"abcd");
+    receive(&sim);
+    assert_eq!(dma_uart.read(&mut buf[..1]).unwrap(), 1);
+    assert_eq!(buf[0], b'a');
+
+    // The next block overwrites the unread tail
+    uart.push_input(b"efgh");
+    receive(&sim);
+    assert_eq!(dma_uart.read(&mut buf), Err(DdmaError::Overrun));
+    assert_eq!(
+        embedded_io::Error::kind(&DdmaError::Overrun),
+        embedded_io::ErrorKind::Other
+    );
+
+    // Reading carries on with the newer characters
+    assert_eq!(dma_uart.read(&mut buf).unwrap(), CHARS);
+    assert_eq!(&buf, b"efgh");
+    uart.push_input(b"i");
+    assert_eq!(dma_uart.read(&mut buf).unwrap(), 1);
+    assert_eq!(buf[0], b'i');
+}
+
+#[test]
+fn bounded_read_times_out_without_input() {
+    let (_sim, uart, mut dma_uart) = dma_uart();
+    let mut buf = [0; CHARS];
+
+    assert_eq!(
+        dma_uart.read_with_budget(&mut buf, 1000),
+        Err(DdmaError::Timeout)
+    );
+    uart.push_input(b"j");
+    assert_eq!(dma_uart.read(&mut buf).unwrap(), 1);
+    assert_eq!(buf[0], b'j');
+}
@@ -0,0 +1,124 @@
+//! Double-buffered streaming on the simulated controller
+//!
+//! Runs on the host:
+//!
+//! ```bash
+//! cargo test --features sim --test sim_double_buffer --target x86_64-unknown-linux-gnu
+//! ```
+
+mod common;
+
+use phytium_ddma::{
+    ChannelConfig, DDMA, DdmaError, DmaDirection, DoubleBuffer, DoubleBufferReader, FilledBuffer,
+    RawRequest, peripheral_ids, sim::SimUart,
+};
+
+use common::{UART1_DR, setup};
+
+/// Characters in each block
+const CHARS: usize = 4;
+/// Polls before a block is considered stuck
+const MAX_POLLS: usize = 10_000;
+
+fn config(blk_size: usize) -> ChannelConfig {
+    let request = RawRequest::new(
+        peripheral_ids::UART1_RX,
+        DmaDirection::DeviceToMemory,
+        UART1_DR,
+    )
+    .unwrap();
+    ChannelConfig::for_request(request, blk_size, true)
+}
+
+/// Send one block of characters and poll until the channel completes it,
+/// returning the result of the completing `on_complete`
+fn block(dma: &DDMA, uart: &SimUart, stream: &mut DoubleBuffer, data: &[u8]) -> bool {
+    uart.push_input(data);
+    for _ in 0..MAX_POLLS {
+        let completed = dma.is_transfer_complete(stream.channel().index());
+        let handed = stream.on_complete().unwrap();
+        if handed || completed {
+            return handed;
+        }
+    }
+    panic!("block did not complete");
+}
+
+fn chars(buffer: &FilledBuffer<'_>) -> Vec<u8> {
+    (0..CHARS)
+        .map(|i| buffer.buff().get(i * 4).unwrap())
+        .collect()
+}
+
+fn take(reader: &mut DoubleBufferReader) -> (Vec<u8>, bool) {
+    let buffer = reader.take().expect("no filled buffer");
+    (chars(&buffer), buffer.overrun())
+}
+
+#[test]
+fn invalid_block_size() {
+    let (_sim, _uart, dma) = setup();
+    for blk_size in [0, 6] {
+        assert_eq!(
+            dma.request_double_buffer(config(blk_size)).err(),
+            Some(DdmaError::InvalidSize)
+        );
+    }
+}
+
+#[test]
+fn blocks_alternate() {
+    let (_sim, uart, dma) = setup();
+    let (mut stream, mut reader) = dma.request_double_buffer(config(CHARS * 4)).unwrap();
+    stream.start().unwrap();
+
+    for data in [b"abcd", b"efgh", b"ijkl"] {
+        assert!(block(&dma, &uart, &mut stream, data));
+        assert_eq!(take(&mut reader), (data.to_vec(), false));
+    }
+    assert!(reader.take().is_none());
+    assert_eq!(reader.overruns(), 0);
+}
+
+#[test]
+fn untaken_block_is_dropped() {
+    let (_sim, uart, dma) = setup();
+    let (mut stream, mut reader) = dma.request_double_buffer(config(CHARS * 4)).unwrap();
+    stream.start().unwrap();
+
+    // The channel refills the buffer of the untaken block
+    assert!(block(&dma, &uart, &mut stream, b"abcd"));
+    assert!(block(&dma, &uart, &mut stream, b"efgh"));
+    assert_eq!(reader.overruns(), 1);
+    assert_eq!(take(&mut reader), (b"efgh".to_vec(), true));
+
+    // Taken in time, the next block is not an overrun
+    assert!(block(&dma, &uart, &mut stream, b"ijkl"));
+    assert_eq!(take(&mut reader), (b"ijkl".to_vec(), false));
+    assert!(reader.take().is_none());
+}
+
+#[test]
+fn held_buffer_is_not_overwritten() {
+    let (_sim, uart, dma) = setup();
+    let (mut stream, mut reader) = dma.request_double_buffer(config(CHARS * 4)).unwrap();
+    stream.start().unwrap();
+
+    assert!(block(&dma, &uart, &mut stream, b"abcd"));
+    let held = reader.take().unwrap();
+
+    // The consumer holds the other buffer, so the block is refilled in place
+    // and nothing is handed over
+    assert!(!block(&dma, &uart, &mut stream, b"efgh"));
+    assert_eq!(chars(&held), b"abcd");
+    assert!(stream.channel().is_running());
+    drop(held);
+    assert!(reader.take().is_none());
+
+    assert!(block(&dma, &uart, &mut stream, b"ijkl"));
+
+    let (last, overrun) = take(&mut reader);
+    assert_eq!(last, b"ijkl");
+    assert!(overrun);
+    assert_eq!(reader.overruns(), 1);
+}
@@ -0,0 +1,244 @@
+//! A peripheral driver written against the `DmaEngine` traits, run on a mock
+//! engine and on the simulated controller
+//!
+//! Runs on the host:
+//!
+//! ```bash
+//! cargo test --features sim --test sim_engine --target x86_64-unknown-linux-gnu
+//! ```
+
+mod common;
+
+use std::{cell::RefCell, rc::Rc};
+
+use phytium_ddma::{
+    ChannelConfig, DdmaError, DmaDirection, DmaEngine, DmaSlaveChannel, RawRequest, TransferStatus,
+    peripheral_ids,
+};
+
+use common::{UART1_DR, drain, setup, words};
+
+/// Polls before a transfer is considered stuck
+const MAX_POLLS: usize = 10_000;
+
+/// Peripheral driver under test: sends `data` through a channel of `engine`
+fn send<E: DmaEngine>(engine: &E, config: &ChannelConfig, data: &[u8]) -> Result<(), E::Error> {
+    let mut channel = engine.request_channel(config)?;
+    channel.write_buffer(0, data)?;
+    channel.prepare_single(data.len())?;
+    channel.submit()?;
+    for _ in 0..MAX_POLLS {
+        if channel.status() == TransferStatus::Complete {
+            return Ok(());
+        }
+    }
+    panic!("transfer did not complete");
+}
+
+fn uart1(direction: DmaDirection, blk_size: usize) -> ChannelConfig {
+    let slave_id = match direction {
+        DmaDirection::MemoryToDevice => peripheral_ids::UART1_TX,
+        DmaDirection::DeviceToMemory => peripheral_ids::UART1_RX,
+    };
+    let request = RawRequest::new(slave_id, direction, UART1_DR).unwrap();
+    ChannelConfig::for_request(request, blk_size, false)
+}
+
+/// Transfers seen by the mock engine
+#[derive(Default)]
+struct Log {
+    sent: Vec<u8>,
+    submits: usize,
+}
+
+/// Engine whose transfers complete after a fixed number of status polls
+struct MockEngine {
+    polls: usize,
+    log: Rc<RefCell<Log>>,
+}
+
+struct MockChannel {
+    buffer: Vec<u8>,
+    len: usize,
+    cyclic: bool,
+    remaining: Option<usize>,
+    polls: usize,
+    log: Rc<RefCell<Log>>,
+}
+
+impl DmaEngine for MockEngine {
+    type Channel = MockChannel;
+    type Error = DdmaError;
+
+    fn request_channel(&self, config: &ChannelConfig) -> Result<MockChannel, DdmaError> {
+        Ok(MockChannel {
+            buffer: vec![0; config.blk_size],
+            len: 0,
+            cyclic: false,
+            remaining: None,
+            polls: self.polls,
+            log: self.log.clone(),
+        })
+    }
+}
+
+impl DmaSlaveChannel for MockChannel {
+    type Error = DdmaError;
+
+    fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+
+    fn write_buffer(&mut self, offset: usize, data: &[u8]) -> Result<(), DdmaError> {
+        let end = offset
+            .checked_add(data.len())
+            .ok_or(DdmaError::InvalidSize)?;
+        self.buffer
+            .get_mut(offset..end)
+            .ok_or(DdmaError::InvalidSize)?
+            .copy_from_slice(data);
+        Ok(())
+    }
+
+    fn read_buffer(&self, offset: usize, data: &mut [u8]) -> Result<(), DdmaError> {
+        let end = offset
+            .checked_add(data.len())
+            .ok_or(DdmaError::InvalidSize)?;
+        data.copy_from_slice(self.buffer.get(offset..end).ok_or(DdmaError::InvalidSize)?);
+        Ok(())
+    }
+
+    fn prepare_single(&mut self, len: usize) -> Result<(), DdmaError> {
+        if len > self.capacity() {
+            return Err(DdmaError::InvalidSize);
+        }
+        self.len = len;
+        self.cyclic = false;
+        Ok(())
+    }
+
+    fn prepare_cyclic(&mut self, len: usize) -> Result<(), DdmaError> {
+        self.prepare_single(len)?;
+        self.cyclic = true;
+        Ok(())
+    }
+
+    fn submit(&mut self) -> Result<(), DdmaError> {
+        if self.remaining.is_some() {
+            return Err(DdmaError::Busy);
+        }
+        self.remaining = Some(self.polls);
+        self.log.borrow_mut().submits += 1;
+        Ok(())
+    }
+
+    fn status(&mut self) -> TransferStatus {
+        match self.remaining {
+            None => TransferStatus::Idle,
+            Some(0) => {
+                let mut log = self.log.borrow_mut();
+                log.sent.extend_from_slice(&self.buffer[..self.len]);
+                self.remaining = self.cyclic.then_some(self.polls);
+                TransferStatus::Complete
+            }
+            Some(n) => {
+                self.remaining = Some(n - 1);
+                TransferStatus::InProgress
+            }
+        }
+    }
+
+    fn terminate(&mut self) {
+        self.remaining = None;
+    }
+}
+
+#[test]
+fn send_on_mock_engine() {
+    let log = Rc::new(RefCell::new(Log::default()));
+    let engine = MockEngine {
+        polls: 3,
+        log: log.clone(),
+    };
+    let config = uart1(DmaDirection::MemoryToDevice, 16);
+
+    send(&engine, &config, b"mock").unwrap();
+    assert_eq!(log.borrow().sent, b"mock");
+    assert_eq!(log.borrow().submits, 1);
+    assert_eq!(
+        send(&engine, &config, &[0; 17]),
+        Err(DdmaError::InvalidSize)
+    );
+}
+
+#[test]
+fn send_on_ddma() {
+    let (sim, uart, dma) = setup();
+    let msg = b"engine";
+    let data = words(msg);
+
+    send(&dma, &uart1(DmaDirection::MemoryToDevice, 64), &data).unwrap();
+    drain(&sim, &uart);
+    assert_eq!(uart.output(), msg);
+    assert_eq!(sim.bus_errors(), 0);
+}
+
+#[test]
+fn raw_request_is_checked() {
+    assert_eq!(
+        RawRequest::new(32, DmaDirection::MemoryToDevice, UART1_DR).err(),
+        Some(DdmaError::InvalidSlaveId)
+    );
+    let config = uart1(DmaDirection::DeviceToMemory, 16);
+    assert_eq!(config.slave_id(), peripheral_ids::UART1_RX);
+    assert_eq!(config.direction(), DmaDirection::DeviceToMemory);
+    assert_eq!(config.dev_addr(), UART1_DR);
+}
+
+#[test]
+fn buffer_bounds() {
+    let (_sim, _uart, dma) = setup();
+    let mut channel =
+        DmaEngine::request_channel(&dma, &uart1(DmaDirection::MemoryToDevice, 16)).unwrap();
+
+    channel.write_buffer(12, &[1, 2, 3, 4]).unwrap();
+    assert_eq!(
+        channel.write_buffer(13, &[1, 2, 3, 4]),
+        Err(DdmaError::InvalidSize)
+    );
+    assert_eq!(
+        channel.write_buffer(usize::MAX, &[1]),
+        Err(DdmaError::InvalidSize)
+    );
+    let mut data = [0; 4];
+    channel.read_buffer(12, &mut data).unwrap();
+    assert_eq!(data, [1, 2, 3, 4]);
+    assert_eq!(
+        channel.read_buffer(usize::MAX - 1, &mut data),
+        Err(DdmaError::InvalidSize)
+    );
+}
+
+#[test]
+fn cyclic_restarts_when_status_is_polled() {
+    let (_sim, uart, dma) = setup();
+    let mut channel =
+        DmaEngine::request_channel(&dma, &uart1(DmaDirection::DeviceToMemory, 8)).unwrap();
+    channel.prepare_cyclic(8).unwrap();
+    channel.submit().unwrap();
+
+    for period in [b"ab", b"cd"] {
+        uart.push_input(period);
+        let mut polls = 0;
+        while channel.status() != TransferStatus::Complete {
+            polls += 1;
+            assert!(polls < MAX_POLLS, "period did not complete");
+        }
+        let mut data = [0; 8];
+        channel.read_buffer(0, &mut data).unwrap();
+        assert_eq!([data[0], data[4]], *period);
+    }
+    assert_eq!(channel.status(), TransferStatus::InProgress);
+    channel.terminate();
+    assert_eq!(channel.status(), TransferStatus::Idle);
+}
@@ -0,0 +1,213 @@
+//! Error paths of the driver under faults injected into the simulated
+//! controller
+//!
+//! Runs on the host:
+//!
+//! ```bash
+//! cargo test --features sim --test sim_faults --target x86_64-unknown-linux-gnu
+//! ```
+
+mod common;
+
+use std::time::Duration;
+
+use phytium_ddma::{DdmaError, DmaDirection, DmaTransfer, RetryPolicy, sim::Faults, time::Clock};
+
+use common::{UART1_DR, drain, setup, uart_config, uart_rx, uart_tx};
+
+/// Simulated time given to a transfer, far more than it needs
+const TIMEOUT: Duration = Duration::from_millis(1);
+
+#[test]
+fn stuck_enable_fails_rebind() {
+    let (sim, _uart, dma) = setup();
+
+    // Waiting for input, the channel stays enabled
+    let (mut channel, _) = uart_rx(&sim, &dma, 0, 4);
+    channel.clear_and_active(&dma);
+    sim.inject(Faults {
+        stuck_enable: 1 << 0,
+        ..Default::default()
+    });
+    drop(channel);
+
+    let config = uart_config(0, DmaDirection::DeviceToMemory);
+    let transfer = DmaTransfer {
+        mem_addr: sim.alloc(16),
+        dev_addr: UART1_DR,
+        size: 16,
+    };
+    // Stopping the channel gives up after the wait budget, one step per poll
+    dma.set_wait_budget(1000);
+    let start = sim.now();
+    assert_eq!(
+        dma.prepare_transfer(&config, &transfer).err(),
+        Some(DdmaError::Timeout)
+    );
+    let elapsed = sim.now() - start;
+    assert!(elapsed >= Duration::from_micros(1000));
+    assert!(elapsed < Duration::from_micros(1100));
+
+    // The channel is left unbound, the controller running and the other
+    // channels usable
+    let (dma_ctl, _, bind, _) = dma.debug_status(0);
+    assert_eq!(dma_ctl & 1, 1);
+    assert_eq!(bind & 1, 0);
+    uart_rx(&sim, &dma, 1, 4);
+}
+
+#[test]
+fn stuck_fifo_times_out_then_recovers() {
+    let (sim, uart, dma) = setup();
+    let msg = b"stuck";
+    let (mut channel, _) = uart_tx(&sim, &dma, 0, msg);
+
+    sim.inject(Faults {
+        stuck_fifo: 1 << 0,
+        ..Default::default()
+    });
+    channel.clear_and_active(&dma);
+    assert_eq!(
+        channel.wait_complete_with(&sim, TIMEOUT),
+        Err(DdmaError::Timeout)
+    );
+    assert_eq!(channel.transferred().unwrap(), 0);
+    channel.deactive();
+    assert!(!channel.is_running());
+
+    // Restarted once the FIFO drains, the transfer goes through
+    sim.inject(Faults::default());
+    channel.clear_and_active(&dma);
+    channel.wait_complete_with(&sim, TIMEOUT).unwrap();
+    drain(&sim, &uart);
+    assert_eq!(uart.output(), msg);
+}
+
+#[test]
+fn lost_completion_times_out() {
+    let (sim, uart, dma) = setup();
+    let msg = b"lost";
+    let (mut channel, _) = uart_tx(&sim, &dma, 0, msg);
+
+    sim.inject(Faults {
+        lost_completion: 1 << 0,
+        ..Default::default()
+    });
+    channel.clear_and_active(&dma);
+    assert_eq!(
+        channel.wait_complete_with(&sim, TIMEOUT),
+        Err(DdmaError::Timeout)
+    );
+    assert!(!sim.irq_asserted());
+
+    // The data went out, only the status is missing
+    assert_eq!(channel.transferred().unwrap(), msg.len() * 4);
+    drain(&sim, &uart);
+    assert_eq!(uart.output(), msg);
+}
+
+#[test]
+fn spurious_status_is_not_a_completion() {
+    let (sim, _uart, dma) = setup();
+    let (mut channel, _) = uart_rx(&sim, &dma, 0, 4);
+
+    // A stale bit of the channel is acknowledged when it is started
+    sim.raise_status(1 << 0);
+    channel.clear_and_active(&dma);
+    assert!(!channel.poll_complete());
+
+    // A bit of another channel does not complete this one
+    sim.raise_status(1 << 4);
+    assert!(!channel.poll_complete());
+    assert_eq!(
+        dma.wait_any_with(&sim, 1 << 0, TIMEOUT).err(),
+        Some(DdmaError::Timeout)
+    );
+    assert!(channel.is_running());
+}
+
+#[test]
+fn retry_resets_only_the_failed_channel() {
+    let (sim, uart, dma) = setup();
+    let msg = b"retry";
+    let (mut tx, _) = uart_tx(&sim, &dma, 0, msg);
+    let (mut rx, _) = uart_rx(&sim, &dma, 1, 4);
+    rx.clear_and_active(&dma);
+
+    sim.inject(Faults {
+        stuck_fifo: 1 << 0,
+        ..Default::default()
+    });
+    tx.clear_and_active(&dma);
+    assert_eq!(
+        tx.wait_complete_with(&sim, TIMEOUT),
+        Err(DdmaError::Timeout)
+    );
+
+    // Channel 0 is soft-reset and restarted while channel 1 keeps running
+    assert!(tx.retry().unwrap());
+    assert_eq!(tx.retries(), 1);
+    assert_eq!(tx.transferred().unwrap(), 0);
+    assert!(rx.is_running());
+    sim.inject(Faults::default());
+
+    uart.push_input(b"pong");
+    tx.wait_complete_with(&sim, TIMEOUT).unwrap();
+    assert_eq!(tx.retries(), 0);
+    rx.wait_complete_with(&sim, TIMEOUT).unwrap();
+    drain(&sim, &uart);
+    assert_eq!(uart.output(), msg);
+}
+
+#[test]
+fn transfer_gives_up_after_retry_policy() {
+    let (sim, uart, dma) = setup();
+    let msg = b"again";
+    let (mut channel, _) = uart_tx(&sim, &dma, 0, msg);
+    channel.set_retry_policy(RetryPolicy { max_retries: 2 });
+
+    sim.inject(Faults {
+        lost_completion: 1 << 0,
+        ..Default::default()
+    });
+    assert_eq!(
+        channel.transfer_with(&sim, TIMEOUT),
+        Err(DdmaError::Timeout)
+    );
+    assert_eq!(channel.retries(), 2);
+    assert!(!channel.is_running());
+    assert!(!channel.is_submitted());
+
+    // Every attempt sent the whole block again
+    drain(&sim, &uart);
+    assert_eq!(uart.output(), msg.repeat(3));
+}
+
+#[test]
+fn irq_handler_ignores_masked_and_spurious() {
+    let (sim, _uart, dma) = setup();
+    let handler = dma.irq_handler();
+
+    // Nothing pending: the interrupt is not from this controller
+    assert!(handler.handle_irq().is_none());
+
+    // A completion of a masked channel is neither reported nor acknowledged
+    sim.raise_status(1 << 8);
+    assert!(!sim.irq_asserted());
+    assert!(handler.handle_irq().is_none());
+    assert!(dma.is_transfer_complete(2));
+
+    let (mut channel, _) = uart_tx(&sim, &dma, 0, b"irq");
+    channel.clear_and_active(&dma);
+    while !sim.irq_asserted() {
+        sim.step();
+    }
+    let completed = handler.handle_irq().unwrap();
+    assert_eq!(completed.bitmask(), 1 << 0);
+    assert!(!sim.irq_asserted());
+    assert!(dma.is_transfer_complete(2));
+    assert!(channel.poll_complete());
+
+    #[cfg(feature = "stats")]
+    assert_eq!(dma.spurious_irqs(), 2);
+}
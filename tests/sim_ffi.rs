@@ -0,0 +1,159 @@
+//! The SDK-style C API driving the simulated controller
+//!
+//! Runs on the host:
+//!
+//! ```bash
+//! cargo test --features sim,ffi --test sim_ffi --target x86_64-unknown-linux-gnu
+//! ```
+
+mod common;
+
+use std::{
+    ffi::c_void,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use phytium_ddma::{
+    ffi::*,
+    peripheral_ids,
+    sim::{SimUart, Simulator},
+};
+
+use common::{UART1_DR, drain, words};
+
+/// Polls before a transfer is considered stuck
+const MAX_POLLS: usize = 10_000;
+
+fn instance() -> FDdma {
+    FDdma {
+        config: FDdmaConfig {
+            id: 0,
+            base_addr: 0,
+            irq_num: 0,
+            irq_prority: 0,
+        },
+        is_ready: 0,
+        priv_: std::ptr::null_mut(),
+    }
+}
+
+/// Completion callback counting the events of each channel in `args`
+unsafe extern "C" fn on_done(chan_idx: u32, args: *mut c_void) {
+    let done = unsafe { &*args.cast::<[AtomicU32; 8]>() };
+    done[chan_idx as usize].fetch_add(1, Ordering::Relaxed);
+}
+
+#[test]
+fn sdk_call_sequence() {
+    let sim = Simulator::new();
+    let uart = SimUart::new(8);
+    sim.attach(
+        peripheral_ids::UART1_TX,
+        peripheral_ids::UART1_RX,
+        uart.clone(),
+    );
+    let msg = b"from C\n";
+    let data = words(msg);
+    let ddr_addr = sim.alloc(data.len());
+    sim.write_mem(ddr_addr, &data);
+
+    let mut dma = instance();
+    let config = FDdmaConfig {
+        id: 0,
+        base_addr: sim.base().as_ptr() as usize,
+        irq_num: 107,
+        irq_prority: 0,
+    };
+    let chan_config = FDdmaChanConfig {
+        slave_id: peripheral_ids::UART1_TX as u32,
+        req_mode: FDDMA_CHAN_REQ_TX,
+        ddr_addr: ddr_addr as usize,
+        dev_addr: UART1_DR,
+        trans_len: data.len() as u32,
+        timeout: 0,
+    };
+    let done: [AtomicU32; 8] = Default::default();
+
+    unsafe {
+        assert_eq!(FDdmaCfgInitialize(&mut dma, &config), FDDMA_SUCCESS);
+        assert_eq!(FDdmaCfgInitialize(&mut dma, &config), FDDMA_ERR_IS_USED);
+        assert_eq!(FDdmaChanConfigure(&mut dma, 0, &chan_config), FDDMA_SUCCESS);
+        assert_eq!(
+            FDdmaChanConfigure(&mut dma, 0, &chan_config),
+            FDDMA_ERR_CHAN_BINDED
+        );
+        assert_eq!(
+            FDdmaChanConfigure(&mut dma, 8, &chan_config),
+            FDDMA_ERR_INVALID_INPUT
+        );
+        FDdmaRegisterChanEvtHandler(
+            &mut dma,
+            0,
+            FDDMA_CHAN_EVT_REQ_DONE,
+            Some(on_done),
+            (&done as *const [AtomicU32; 8]).cast_mut().cast(),
+        );
+        assert_eq!(FDdmaChanActive(&mut dma, 0), FDDMA_SUCCESS);
+        assert_eq!(FDdmaStart(&mut dma), FDDMA_SUCCESS);
+    }
+
+    let mut polls = 0;
+    while !sim.irq_asserted() {
+        sim.step();
+        polls += 1;
+        assert!(polls < MAX_POLLS, "no completion interrupt");
+    }
+    unsafe { FDdmaIrqHandler(107, (&mut dma as *mut FDdma).cast()) };
+    assert!(!sim.irq_asserted());
+    assert_eq!(done[0].load(Ordering::Relaxed), 1);
+    drain(&sim, &uart);
+    assert_eq!(uart.output(), msg);
+
+    unsafe {
+        assert_eq!(FDdmaChanDeconfigure(&mut dma, 0), FDDMA_SUCCESS);
+        assert_eq!(FDdmaStop(&mut dma), FDDMA_SUCCESS);
+        FDdmaDeInitialize(&mut dma);
+        assert_eq!(FDdmaChanActive(&mut dma, 0), FDDMA_ERR_NOT_INIT);
+    }
+}
+
+#[test]
+fn invalid_transfer_is_rejected() {
+    let sim = Simulator::new();
+    let mut dma = instance();
+    let config = FDdmaConfig {
+        id: 0,
+        base_addr: sim.base().as_ptr() as usize,
+        irq_num: 107,
+        irq_prority: 0,
+    };
+    let mut chan_config = FDdmaChanConfig {
+        slave_id: peripheral_ids::UART1_TX as u32,
+        req_mode: FDDMA_CHAN_REQ_TX,
+        ddr_addr: sim.alloc(16) as usize,
+        dev_addr: UART1_DR,
+        trans_len: 6,
+        timeout: 0,
+    };
+
+    unsafe {
+        assert_eq!(FDdmaCfgInitialize(&mut dma, &config), FDDMA_SUCCESS);
+        assert_eq!(
+            FDdmaChanConfigure(&mut dma, 0, &chan_config),
+            FDDMA_ERR_INVALID_TRANS_SIZE
+        );
+        chan_config.trans_len = 16;
+        chan_config.ddr_addr += 2;
+        assert_eq!(
+            FDdmaChanConfigure(&mut dma, 0, &chan_config),
+            FDDMA_ERR_INVALID_DDR_ADDR
+        );
+        chan_config.ddr_addr -= 2;
+        chan_config.req_mode = 2;
+        assert_eq!(
+            FDdmaChanConfigure(&mut dma, 0, &chan_config),
+            FDDMA_ERR_INVALID_INPUT
+        );
+        FDdmaDeInitialize(&mut dma);
+    }
+}
@@ -0,0 +1,136 @@
+//! Routing and interrupt dispatch of the manager over two simulated
+//! controllers
+//!
+//! Runs on the host:
+//!
+//! ```bash
+//! cargo test --features sim --test sim_manager --target x86_64-unknown-linux-gnu
+//! ```
+
+mod common;
+
+use phytium_ddma::{
+    Channel, DdmaError, DdmaManager, DmaDirection, DmaSlaveChannel, RawRequest, peripheral_ids,
+    sim::{SimUart, Simulator},
+};
+
+use common::{UART1_DR, drain, setup, words};
+
+/// Interrupt of DMA0
+const IRQ0: usize = 107;
+/// Interrupt of DMA1
+const IRQ1: usize = 108;
+
+/// Two simulated controllers with a UART each, added DMA1 first
+fn manager(irq0: usize, irq1: usize) -> (DdmaManager, [(Simulator, SimUart); 2]) {
+    let (sim0, uart0, dma0) = setup();
+    let (sim1, uart1, dma1) = setup();
+    let mut manager = DdmaManager::new();
+    manager.add(1, dma1, irq1).unwrap();
+    manager.add(0, dma0, irq0).unwrap();
+    (manager, [(sim0, uart0), (sim1, uart1)])
+}
+
+/// Send `msg` to UART1 of controller `controller`
+fn send(manager: &DdmaManager, controller: usize, msg: &[u8]) -> Channel {
+    let request = RawRequest::new(
+        peripheral_ids::UART1_TX,
+        DmaDirection::MemoryToDevice,
+        UART1_DR,
+    )
+    .unwrap()
+    .on_controller(controller);
+    let words = words(msg);
+    let mut channel = manager.request_channel(request, words.len(), true).unwrap();
+    channel.write_buffer(0, &words).unwrap();
+    channel.prepare_single(words.len()).unwrap();
+    channel.submit().unwrap();
+    channel
+}
+
+#[test]
+fn controllers_are_keyed_by_index() {
+    let (mut manager, _sims) = manager(IRQ0, IRQ1);
+    assert_eq!(manager.len(), 2);
+
+    let (_sim, _uart, dma) = setup();
+    assert_eq!(
+        manager.add(1, dma, IRQ1).err(),
+        Some(DdmaError::ControllerInUse)
+    );
+    assert_eq!(manager.len(), 2);
+    assert!(manager.controller(2).is_none());
+
+    let request = RawRequest::new(
+        peripheral_ids::UART1_TX,
+        DmaDirection::MemoryToDevice,
+        UART1_DR,
+    )
+    .unwrap()
+    .on_controller(2);
+    assert!(manager.request_channel(request, 16, false).is_none());
+}
+
+#[test]
+fn requests_are_routed_to_their_controller() {
+    let (manager, [(sim0, uart0), (sim1, uart1)]) = manager(IRQ0, IRQ1);
+
+    let mut channel = send(&manager, 1, b"dma1");
+    while !channel.poll_complete() {
+        sim1.step();
+    }
+    drain(&sim1, &uart1);
+    assert_eq!(uart1.output(), b"dma1");
+
+    // DMA0 was not touched
+    for _ in 0..100 {
+        sim0.step();
+    }
+    assert!(uart0.output().is_empty());
+    assert_eq!(manager.controller(0).unwrap().debug_status(0).2, 0);
+}
+
+#[test]
+fn interrupts_are_dispatched_by_line() {
+    let (manager, [(sim0, _), (sim1, _)]) = manager(IRQ0, IRQ1);
+    let handler = manager.irq_handler();
+
+    let mut channel = send(&manager, 1, b"irq");
+    while !sim1.irq_asserted() {
+        sim1.step();
+    }
+
+    // The line of DMA0 finds nothing
+    assert!(!handler.handle_irq(IRQ0, |_, _| panic!("DMA0 has no completion")));
+    assert!(sim1.irq_asserted());
+
+    let mut seen = Vec::new();
+    assert!(handler.handle_irq(IRQ1, |index, completed| {
+        seen.push((index, completed.bitmask()))
+    }));
+    assert_eq!(seen, [(1, 1 << 0)]);
+    assert!(!sim1.irq_asserted());
+    assert!(!sim0.irq_asserted());
+    assert!(channel.poll_complete());
+}
+
+#[test]
+fn shared_line_reports_each_controller() {
+    let (manager, [(sim0, _), (sim1, _)]) = manager(IRQ0, IRQ0);
+    let handler = manager.irq_handler();
+
+    let _dma0 = send(&manager, 0, b"zero");
+    let _dma1 = send(&manager, 1, b"one");
+    while !sim0.irq_asserted() || !sim1.irq_asserted() {
+        sim0.step();
+        sim1.step();
+    }
+
+    let mut seen = Vec::new();
+    assert!(handler.handle_irq(IRQ0, |index, completed| {
+        seen.push((index, completed.bitmask()))
+    }));
+    seen.sort();
+    assert_eq!(seen, [(0, 1 << 0), (1, 1 << 0)]);
+    assert!(!handler.handle_irq(IRQ0, |_, _| panic!("already handled")));
+}
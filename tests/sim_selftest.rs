@@ -0,0 +1,52 @@
+//! Controller self-test on the simulated controller
+//!
+//! Runs on the host:
+//!
+//! ```bash
+//! cargo test --features sim --test sim_selftest --target x86_64-unknown-linux-gnu
+//! ```
+
+mod common;
+
+use phytium_ddma::{CheckResult, DDMA, DdmaError, sim::Simulator};
+
+use common::{drain, setup, uart_tx};
+
+#[test]
+fn self_test_passes() {
+    let sim = Simulator::new();
+    let dma = DDMA::new(sim.base());
+
+    let report = dma.self_test().unwrap();
+    assert!(report.passed(), "{report:?}");
+    assert_eq!(report.channel_count, 8);
+    assert_eq!(report.reset_values, CheckResult::Passed);
+    assert_eq!(report.channel_reset, CheckResult::Passed);
+
+    // Left in the reset state of the driver
+    let (dma_ctl, dma_stat, bind, mask) = dma.debug_status(0);
+    assert_eq!(dma_ctl & 1, 0);
+    assert_eq!(dma_stat, 0);
+    assert_eq!(bind, 0);
+    assert_eq!(mask, u32::MAX);
+}
+
+#[test]
+fn self_test_after_transfers() {
+    let (sim, uart, dma) = setup();
+    let msg = b"before the self-test";
+
+    let (mut channel, _) = uart_tx(&sim, &dma, 3, msg);
+    channel.clear_and_active(&dma);
+    while !channel.poll_complete() {}
+    // Refused while the channel is held
+    assert_eq!(dma.self_test(), Err(DdmaError::Busy));
+    let (_, _, bind, _) = dma.debug_status(0);
+    assert_ne!(bind & (1 << channel.index()), 0);
+    drop(channel);
+    drain(&sim, &uart);
+
+    // The soft resets clear what the transfer left behind
+    let report = dma.self_test().unwrap();
+    assert!(report.passed(), "{report:?}");
+}
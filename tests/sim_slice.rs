@@ -0,0 +1,210 @@
+//! Transfers of byte slices on the simulated controller
+//!
+//! Runs on the host:
+//!
+//! ```bash
+//! cargo test --features sim --test sim_slice --target x86_64-unknown-linux-gnu
+//! ```
+
+mod common;
+
+use std::{
+    collections::VecDeque,
+    ptr::NonNull,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use phytium_ddma::{
+    Channel, ChannelConfig, DDMA, DdmaError, DmaDirection, RawRequest, TailPolicy, peripheral_ids,
+    sim::{Faults, Peripheral, Simulator, map_host, unmap_host},
+};
+
+use common::setup;
+
+/// UART0 data register, used as the FIFO of the recorder
+const FIFO: u32 = 0x2800_C000;
+/// Simulated time given to a transfer, far more than it needs
+const TIMEOUT: Duration = Duration::from_millis(1);
+
+/// Peripheral keeping the words it is sent and supplying queued words
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<(Vec<u32>, VecDeque<u32>)>>);
+
+impl Recorder {
+    fn sent(&self) -> Vec<u32> {
+        std::mem::take(&mut self.0.lock().unwrap().0)
+    }
+
+    fn supply(&self, words: &[u32]) {
+        self.0.lock().unwrap().1.extend(words);
+    }
+}
+
+impl Peripheral for Recorder {
+    fn tx_request(&self) -> bool {
+        true
+    }
+
+    fn rx_request(&self) -> bool {
+        !self.0.lock().unwrap().1.is_empty()
+    }
+
+    fn write(&mut self, word: u32) {
+        self.0.lock().unwrap().0.push(word);
+    }
+
+    fn read(&mut self) -> u32 {
+        self.0.lock().unwrap().1.pop_front().unwrap_or_default()
+    }
+}
+
+/// A channel with a buffer of 16 bytes on the recorder
+fn channel(direction: DmaDirection) -> (Simulator, DDMA, Recorder, Channel) {
+    let (sim, _uart, dma) = setup();
+    let recorder = Recorder::default();
+    sim.attach(
+        peripheral_ids::UART0_TX,
+        peripheral_ids::UART0_RX,
+        recorder.clone(),
+    );
+    let slave_id = match direction {
+        DmaDirection::MemoryToDevice => peripheral_ids::UART0_TX,
+        DmaDirection::DeviceToMemory => peripheral_ids::UART0_RX,
+    };
+    let request = RawRequest::new(slave_id, direction, FIFO).unwrap();
+    let channel = dma
+        .request_channel(ChannelConfig::for_request(request, 16, false))
+        .unwrap();
+    (sim, dma, recorder, channel)
+}
+
+#[test]
+fn write_pad() {
+    let (sim, _dma, recorder, mut channel) = channel(DmaDirection::MemoryToDevice);
+
+    let mut transfer = channel
+        .write_slice(&[1, 2, 3, 4, 5], TailPolicy::Pad)
+        .unwrap();
+    assert_eq!(transfer.len(), 5);
+    assert!(transfer.pio_tail().is_empty());
+    transfer.wait_complete_with(&sim, TIMEOUT).unwrap();
+    drop(transfer);
+    assert_eq!(recorder.sent(), [0x0403_0201, 0x0000_0005]);
+}
+
+#[test]
+fn write_pio() {
+    let (sim, _dma, recorder, mut channel) = channel(DmaDirection::MemoryToDevice);
+
+    // Longer than the buffer: cut to its size, with no tail left
+    let data: Vec<u8> = (1..=20).collect();
+    let mut transfer = channel.write_slice(&data, TailPolicy::Pio).unwrap();
+    assert_eq!(transfer.len(), 16);
+    assert!(transfer.pio_tail().is_empty());
+    transfer.wait_complete_with(&sim, TIMEOUT).unwrap();
+    drop(transfer);
+    assert_eq!(recorder.sent().len(), 4);
+
+    let mut transfer = channel.write_slice(&data[..7], TailPolicy::Pio).unwrap();
+    assert_eq!(transfer.pio_tail(), 4..7);
+    transfer.wait_complete_with(&sim, TIMEOUT).unwrap();
+    drop(transfer);
+    assert_eq!(recorder.sent(), [0x0403_0201]);
+
+    // Shorter than a word: nothing for the controller
+    let mut transfer = channel.write_slice(&data[..3], TailPolicy::Pio).unwrap();
+    assert!(transfer.poll());
+    assert_eq!(transfer.pio_tail(), 0..3);
+}
+
+#[test]
+fn read_pad_and_pio() {
+    let (sim, _dma, recorder, mut channel) = channel(DmaDirection::DeviceToMemory);
+
+    recorder.supply(&[0x4433_2211, 0x8877_6655]);
+    let mut data = [0; 6];
+    let mut transfer = channel.read_slice(&mut data, TailPolicy::Pad).unwrap();
+    transfer.wait_complete_with(&sim, TIMEOUT).unwrap();
+    drop(transfer);
+    assert_eq!(data, [0x11, 0x22, 0x33, 0x44, 0x55, 0x66]);
+
+    recorder.supply(&[0xDDCC_BBAA]);
+    let mut data = [0; 6];
+    let mut transfer = channel.read_slice(&mut data, TailPolicy::Pio).unwrap();
+    assert_eq!(transfer.pio_tail(), 4..6);
+    transfer.wait_complete_with(&sim, TIMEOUT).unwrap();
+    drop(transfer);
+    assert_eq!(data, [0xAA, 0xBB, 0xCC, 0xDD, 0, 0]);
+}
+
+#[test]
+fn in_place() {
+    let (sim, _dma, recorder, mut channel) = channel(DmaDirection::MemoryToDevice);
+    let mut words: [u32; 6] = std::array::from_fn(|i| {
+        let b = 4 * i as u32 + 1;
+        u32::from_le_bytes([b as u8, (b + 1) as u8, (b + 2) as u8, (b + 3) as u8])
+    });
+    let ptr = NonNull::from(&mut words).cast::<u8>();
+    let data = unsafe { std::slice::from_raw_parts(ptr.as_ptr(), 24) };
+    let bus_addr = unsafe { map_host(ptr, 24) };
+
+    // Aligned, the whole slice is moved, more than the channel buffer holds
+    let mut transfer =
+        unsafe { channel.write_slice_in_place(data, bus_addr, TailPolicy::Pio) }.unwrap();
+    assert_eq!(transfer.len(), 24);
+    transfer.wait_complete_with(&sim, TIMEOUT).unwrap();
+    drop(transfer);
+    assert_eq!(recorder.sent(), words);
+
+    // Unaligned, it goes through the channel buffer
+    let mut transfer =
+        unsafe { channel.write_slice_in_place(&data[1..9], bus_addr + 1, TailPolicy::Pad) }
+            .unwrap();
+    transfer.wait_complete_with(&sim, TIMEOUT).unwrap();
+    drop(transfer);
+    assert_eq!(recorder.sent(), [0x0504_0302, 0x0908_0706]);
+    unmap_host(ptr);
+}
+
+#[test]
+fn cyclic_mode_is_restored() {
+    let (sim, dma, recorder, mut channel) = channel(DmaDirection::MemoryToDevice);
+    channel.prepare(8, true).unwrap();
+
+    // Dropped before completion
+    let transfer = channel.write_slice(&[0; 16], TailPolicy::Pad).unwrap();
+    drop(transfer);
+    assert!(!channel.is_running());
+    recorder.sent();
+
+    // The cyclic transfer of the buffer restarts on completion again
+    channel.clear_and_active(&dma);
+    channel.wait_complete_with(&sim, TIMEOUT).unwrap();
+    assert!(channel.is_submitted());
+    channel.wait_complete_with(&sim, TIMEOUT).unwrap();
+    channel.deactive();
+    assert!(recorder.sent().len() >= 4);
+}
+
+#[test]
+fn busy_channel_buffer_is_untouched() {
+    let (sim, _dma, recorder, mut channel) = channel(DmaDirection::MemoryToDevice);
+    channel.buff_mut().copy_from_slice(&[0xAA; 16]);
+    sim.inject(Faults {
+        stuck_fifo: 1 << channel.index(),
+        ..Default::default()
+    });
+    channel.prepare(16, false).unwrap();
+    channel.active();
+
+    assert!(matches!(
+        channel.write_slice(&[1, 2, 3, 4], TailPolicy::Pad),
+        Err(DdmaError::Busy)
+    ));
+    assert!((0..16).all(|i| channel.buff().get(i) == Some(0xAA)));
+
+    sim.inject(Faults::default());
+    channel.wait_complete_with(&sim, TIMEOUT).unwrap();
+    assert_eq!(recorder.sent(), [0xAAAA_AAAA; 4]);
+}
@@ -0,0 +1,210 @@
+//! Transfer statistics of the channels on the simulated controller
+//!
+//! Runs on the host:
+//!
+//! ```bash
+//! cargo test --features sim,stats --test sim_stats --target x86_64-unknown-linux-gnu
+//! ```
+
+mod common;
+
+use std::time::Duration;
+
+use phytium_ddma::{ChannelStats, DDMA, DdmaError, RetryPolicy, sim::Faults};
+
+use common::{setup, uart_rx, uart_tx};
+
+/// Simulated time given to a transfer, far more than it needs
+const TIMEOUT: Duration = Duration::from_millis(1);
+
+#[test]
+fn completed_transfer() {
+    let (sim, _uart, dma) = setup();
+    let msg = b"counted";
+    let (mut channel, _) = uart_tx(&sim, &dma, 0, msg);
+
+    channel.clear_and_active(&dma);
+    channel.wait_complete_with(&sim, TIMEOUT).unwrap();
+    // Nothing left to consume
+    assert!(!channel.poll_complete());
+
+    assert_eq!(
+        channel.stats(),
+        ChannelStats {
+            transfers: 1,
+            bytes: msg.len() as u64 * 4,
+            completions: 1,
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn interrupt_completion_counts_once() {
+    let (sim, _uart, dma) = setup();
+    let (mut channel, _) = uart_tx(&sim, &dma, 0, b"irq");
+
+    channel.clear_and_active(&dma);
+    while !sim.irq_asserted() {
+        sim.step();
+    }
+    dma.irq_handler().handle_irq().unwrap();
+    assert!(channel.poll_complete());
+
+    let stats = channel.stats();
+    assert_eq!(stats.completions, 1);
+    assert_eq!(stats.spurious, 0);
+}
+
+#[test]
+fn stale_status_is_not_counted() {
+    let (sim, uart, dma) = setup();
+    let (mut channel, _) = uart_rx(&sim, &dma, 0, 4);
+
+    // Acknowledged when starting, neither a completion nor spurious
+    sim.raise_status(1 << 0);
+    channel.clear_and_active(&dma);
+    sim.raise_status(1 << 0);
+    dma.clear_transfer_complete(0);
+    assert_eq!(channel.stats().completions, 0);
+    assert_eq!(channel.stats().spurious, 0);
+
+    uart.push_input(b"data");
+    channel.wait_complete_with(&sim, TIMEOUT).unwrap();
+    assert_eq!(channel.stats().completions, 1);
+
+    // Consumed with no transfer in flight
+    sim.raise_status(1 << 0);
+    assert!(channel.poll_complete());
+    assert_eq!(channel.stats().completions, 1);
+    assert_eq!(channel.stats().spurious, 1);
+}
+
+#[test]
+fn timeout_and_abort() {
+    let (sim, _uart, dma) = setup();
+    let (mut channel, _) = uart_rx(&sim, &dma, 0, 4);
+
+    channel.clear_and_active(&dma);
+    assert_eq!(
+        channel.wait_complete_with(&sim, TIMEOUT),
+        Err(DdmaError::Timeout)
+    );
+    channel.deactive();
+    // Already stopped, not aborted twice
+    channel.deactive();
+
+    let stats = channel.stats();
+    assert_eq!(stats.transfers, 1);
+    assert_eq!(stats.timeouts, 1);
+    assert_eq!(stats.aborts, 1);
+    assert_eq!(stats.completions, 0);
+}
+
+#[test]
+fn split_transfer_counts_once() {
+    let (sim, _uart, dma) = setup();
+    let msg = b"blocks";
+    let (mut channel, _) = uart_tx(&sim, &dma, 0, msg);
+    channel.set_max_block_size(8).unwrap();
+    channel.prepare(msg.len() * 4, false).unwrap();
+
+    channel.clear_and_active(&dma);
+    channel.wait_complete_with(&sim, TIMEOUT).unwrap();
+
+    let stats = channel.stats();
+    assert_eq!(stats.transfers, 1);
+    assert_eq!(stats.bytes, msg.len() as u64 * 4);
+    assert_eq!(stats.completions, 1);
+}
+
+#[test]
+fn wait_any_counts_owned_channels_only() {
+    let (sim, _uart, dma) = setup();
+    let (mut channel, _) = uart_rx(&sim, &dma, 0, 4);
+    channel.clear_and_active(&dma);
+
+    assert_eq!(
+        dma.wait_any_with(&sim, 0b1001, TIMEOUT).err(),
+        Some(DdmaError::Timeout)
+    );
+    assert_eq!(channel.stats().timeouts, 1);
+    assert_eq!(dma.channel_stats(3).timeouts, 0);
+}
+
+#[test]
+fn retries_count_one_transfer() {
+    let (sim, _uart, dma) = setup();
+    let (mut channel, _) = uart_tx(&sim, &dma, 0, b"again");
+    channel.set_retry_policy(RetryPolicy { max_retries: 2 });
+
+    sim.inject(Faults {
+        lost_completion: 1 << 0,
+        ..Default::default()
+    });
+    assert_eq!(
+        channel.transfer_with(&sim, TIMEOUT),
+        Err(DdmaError::Timeout)
+    );
+    let stats = channel.stats();
+    assert_eq!(stats.transfers, 1);
+    assert_eq!(stats.timeouts, 3);
+    assert_eq!(stats.aborts, 1);
+
+    // A late completion of the given up transfer is spurious
+    sim.inject(Faults::default());
+    sim.raise_status(1 << 0);
+    assert!(channel.poll_complete());
+    assert_eq!(channel.stats().completions, 0);
+    assert_eq!(channel.stats().spurious, 1);
+
+    channel.reset_stats();
+    channel.transfer_with(&sim, TIMEOUT).unwrap();
+    assert_eq!(channel.stats().transfers, 1);
+    assert_eq!(channel.stats().completions, 1);
+}
+
+#[test]
+fn recovery_and_reset_end_the_transfer() {
+    let (sim, _uart, dma) = setup();
+    let (mut channel, _) = uart_rx(&sim, &dma, 0, 4);
+
+    channel.clear_and_active(&dma);
+    channel.recover(false).unwrap();
+    assert_eq!(channel.stats().aborts, 1);
+
+    // A restarted block carries on the transfer in flight
+    channel.active();
+    channel.recover(true).unwrap();
+    assert_eq!(channel.stats().transfers, 2);
+    assert_eq!(channel.stats().aborts, 1);
+
+    // A completion after the controller reset is not of that transfer
+    dma.reset();
+    assert_eq!(channel.stats().aborts, 2);
+    sim.raise_status(1 << 0);
+    assert!(channel.poll_complete());
+    assert_eq!(channel.stats().completions, 0);
+    assert_eq!(channel.stats().spurious, 1);
+}
+
+#[test]
+fn adopted_running_transfer_is_not_spurious() {
+    let (sim, _uart, boot) = setup();
+    let msg = b"left running";
+
+    // A transfer started before the driver, e.g. by the bootloader
+    let (mut channel, _) = uart_tx(&sim, &boot, 0, msg);
+    channel.clear_and_active(&boot);
+    std::mem::forget(channel);
+    drop(boot);
+
+    let dma = DDMA::new(sim.base());
+    let mut channel = dma.adopt_channel(0).unwrap();
+    channel.wait_complete_with(&sim, TIMEOUT).unwrap();
+
+    let stats = channel.stats();
+    assert_eq!(stats.transfers, 1);
+    assert_eq!(stats.completions, 1);
+    assert_eq!(stats.spurious, 0);
+}
@@ -0,0 +1,106 @@
+//! Address translation on the simulated controller
+//!
+//! Runs on the host:
+//!
+//! ```bash
+//! cargo test --features sim --test sim_translate --target x86_64-unknown-linux-gnu
+//! ```
+
+mod common;
+
+use phytium_ddma::{
+    AddressTranslation, DDMA, DdmaError, DmaDirection, DmaTransfer, peripheral_ids,
+    sim::{SimUart, Simulator},
+};
+
+use common::{UART1_DR, drain, uart_config, words};
+
+/// Offset of the guest physical addresses from the bus addresses
+const GUEST_OFFSET: u64 = 0x40_0000_0000;
+/// Offset of the guest physical address of the UART from its bus address
+const DEV_OFFSET: u64 = 0x1000_0000;
+
+/// Guest whose physical addresses sit above the bus addresses
+struct Guest;
+
+impl AddressTranslation for Guest {
+    fn mem_addr(&self, addr: u64) -> u64 {
+        addr - GUEST_OFFSET
+    }
+
+    fn dev_addr(&self, addr: u64) -> u64 {
+        addr - DEV_OFFSET
+    }
+}
+
+/// Translation putting peripherals above 4 GiB, out of reach of DEV_ADDR
+struct HighDevices;
+
+impl AddressTranslation for HighDevices {
+    fn dev_addr(&self, addr: u64) -> u64 {
+        addr + (1 << 32)
+    }
+}
+
+fn setup(translation: impl AddressTranslation + 'static) -> (Simulator, SimUart, DDMA) {
+    let sim = Simulator::new();
+    let uart = SimUart::new(8);
+    sim.attach(
+        peripheral_ids::UART1_TX,
+        peripheral_ids::UART1_RX,
+        uart.clone(),
+    );
+    let dma = DDMA::with_translation(sim.base(), translation);
+    dma.reset();
+    dma.enable();
+    (sim, uart, dma)
+}
+
+#[test]
+fn translated_addresses_are_programmed() {
+    let (sim, uart, dma) = setup(Guest);
+    let msg = b"guest";
+    let data = words(msg);
+    let bus_addr = sim.alloc(data.len());
+    sim.write_mem(bus_addr, &data);
+
+    let mut channel = dma
+        .prepare_transfer(
+            &uart_config(2, DmaDirection::MemoryToDevice),
+            &DmaTransfer {
+                mem_addr: bus_addr + GUEST_OFFSET,
+                dev_addr: (UART1_DR as u64 + DEV_OFFSET) as u32,
+                size: data.len(),
+            },
+        )
+        .unwrap();
+    let state = dma.channel_state(2).unwrap();
+    assert_eq!(state.mem_addr, bus_addr);
+    assert_eq!(state.dev_addr, UART1_DR);
+
+    channel.clear_and_active(&dma);
+    while !channel.poll_complete() {}
+    drain(&sim, &uart);
+    assert_eq!(uart.output(), msg);
+    assert_eq!(sim.bus_errors(), 0);
+}
+
+#[test]
+fn out_of_range_device_address_is_refused() {
+    let (sim, _uart, dma) = setup(HighDevices);
+    let mem_addr = sim.alloc(16);
+    let transfer = DmaTransfer {
+        mem_addr,
+        dev_addr: UART1_DR,
+        size: 16,
+    };
+
+    assert_eq!(
+        dma.prepare_transfer(&uart_config(2, DmaDirection::MemoryToDevice), &transfer)
+            .err(),
+        Some(DdmaError::InvalidAddress)
+    );
+    // Nothing was bound or programmed
+    assert_eq!(dma.channel_state(2), None);
+    assert_eq!(dma.bound_channels().count(), 0);
+}
@@ -0,0 +1,155 @@
+//! UART1 transfers through the simulated controller
+//!
+//! Runs on the host:
+//!
+//! ```bash
+//! cargo test --features sim --test sim_uart --target x86_64-unknown-linux-gnu
+//! ```
+
+mod common;
+
+use std::time::Duration;
+
+use phytium_ddma::{DDMA, DdmaError, DmaDirection, peripheral_ids};
+
+use common::{UART1_DR, chars, drain, setup, uart_rx, uart_tx, words};
+
+/// Polls before a transfer is considered stuck
+const MAX_POLLS: usize = 10_000;
+
+#[test]
+fn uart1_tx() {
+    let (sim, uart, dma) = setup();
+    let msg = b"hello from ddma\n";
+
+    let (mut channel, _) = uart_tx(&sim, &dma, 0, msg);
+    channel.clear_and_active(&dma);
+
+    let mut polls = 0;
+    while !sim.irq_asserted() {
+        sim.step();
+        polls += 1;
+        assert!(polls < MAX_POLLS, "no completion interrupt");
+    }
+    assert!(
+        dma.irq_handler()
+            .handle_irq()
+            .unwrap()
+            .is_channel_completed(0)
+    );
+    assert!(!sim.irq_asserted());
+    assert!(channel.poll_complete());
+    assert_eq!(channel.transferred().unwrap(), msg.len() * 4);
+
+    drain(&sim, &uart);
+    assert_eq!(uart.output(), msg);
+    assert_eq!(sim.bus_errors(), 0);
+}
+
+#[test]
+fn uart1_rx() {
+    let (sim, uart, dma) = setup();
+    let msg = b"ping";
+
+    let (mut channel, mem_addr) = uart_rx(&sim, &dma, 0, msg.len());
+    channel.clear_and_active(&dma);
+
+    // Nothing is moved until the UART requests it
+    for _ in 0..10 {
+        assert!(!channel.poll_complete());
+    }
+    assert_eq!(channel.transferred().unwrap(), 0);
+
+    uart.push_input(msg);
+    let mut polls = 0;
+    while !channel.poll_complete() {
+        polls += 1;
+        assert!(polls < MAX_POLLS, "transfer did not complete");
+    }
+
+    assert_eq!(chars(&sim, mem_addr, msg.len()), msg);
+    assert_eq!(sim.bus_errors(), 0);
+}
+
+#[test]
+fn split_transfer() {
+    let (sim, uart, dma) = setup();
+    let msg = b"split up";
+
+    // Blocks of two characters
+    let (mut channel, _) = uart_tx(&sim, &dma, 0, msg);
+    channel.set_max_block_size(8).unwrap();
+    channel.prepare(msg.len() * 4, false).unwrap();
+    channel.clear_and_active(&dma);
+
+    // Every block interrupts, only the last one completes the transfer
+    let mut blocks = 0;
+    loop {
+        let mut polls = 0;
+        while !sim.irq_asserted() {
+            sim.step();
+            polls += 1;
+            assert!(polls < MAX_POLLS, "no completion interrupt");
+        }
+        assert!(
+            dma.irq_handler()
+                .handle_irq()
+                .unwrap()
+                .is_channel_completed(0)
+        );
+        blocks += 1;
+        if channel.poll_complete() {
+            break;
+        }
+        assert!(channel.is_submitted());
+    }
+    assert_eq!(blocks, msg.len() / 2);
+    assert_eq!(channel.transferred().unwrap(), msg.len() * 4);
+
+    drain(&sim, &uart);
+    assert_eq!(uart.output(), msg);
+}
+
+#[test]
+fn adopt_console_tx() {
+    let (sim, uart, boot) = setup();
+    let msg = b"U-Boot console output\n";
+
+    // The bootloader starts a transfer and leaves it running
+    let (mut channel, mem_addr) = uart_tx(&sim, &boot, 0, msg);
+    channel.clear_and_active(&boot);
+    for _ in 0..10 {
+        sim.step();
+    }
+    std::mem::forget(channel);
+    drop(boot);
+
+    // The kernel driver finds it without resetting the controller
+    let dma = DDMA::new(sim.base());
+    let states: Vec<_> = dma.bound_channels().collect();
+    assert_eq!(states.len(), 1);
+    let state = states[0];
+    assert_eq!(state.channel, 0);
+    assert_eq!(state.slave_id, peripheral_ids::UART1_TX);
+    assert_eq!(state.direction, DmaDirection::MemoryToDevice);
+    assert_eq!(state.mem_addr, mem_addr);
+    assert_eq!(state.dev_addr, UART1_DR);
+    assert_eq!(state.size, words(msg).len());
+    assert!(state.running);
+    assert!(state.irq);
+    assert!(state.transferred > 0 && state.transferred < state.size);
+
+    let mut channel = dma.adopt_channel(0).unwrap();
+    assert_eq!(dma.adopt_channel(0).err(), Some(DdmaError::ChannelInUse));
+    assert_eq!(dma.adopt_channel(1).err(), Some(DdmaError::NotBound));
+    assert_eq!(dma.bound_channels().count(), 0);
+
+    // The transfer completes on the adopted channel, without a glitch
+    assert!(channel.is_submitted());
+    channel
+        .wait_complete_with(&sim, Duration::from_millis(1))
+        .unwrap();
+    assert_eq!(channel.transferred().unwrap(), state.size);
+    drain(&sim, &uart);
+    assert_eq!(uart.output(), msg);
+}
@@ -0,0 +1,216 @@
+//! Stall detection of the channel watchdog on the simulated controller
+//!
+//! Runs on the host:
+//!
+//! ```bash
+//! cargo test --features sim --test sim_watchdog --target x86_64-unknown-linux-gnu
+//! ```
+
+mod common;
+
+use std::time::Duration;
+
+use phytium_ddma::{
+    Channel, DmaChannelConfig, DmaDirection, DmaTransfer, RetryPolicy, StallAction, Watchdog,
+    WatchdogStatus, peripheral_ids,
+    sim::{FIFO_DEPTH, Faults, Peripheral, Simulator},
+    time::Clock,
+};
+
+use common::{chars, drain, setup, uart_rx, uart_tx};
+
+/// UART0 data register
+const UART0_DR: u32 = 0x2800_C000;
+
+/// Peripheral that never asserts its DMA requests
+struct Blocked;
+
+impl Peripheral for Blocked {
+    fn tx_request(&self) -> bool {
+        false
+    }
+
+    fn rx_request(&self) -> bool {
+        false
+    }
+
+    fn write(&mut self, _word: u32) {}
+
+    fn read(&mut self) -> u32 {
+        0
+    }
+}
+
+/// Stall threshold, in simulated time
+const THRESHOLD: Duration = Duration::from_micros(200);
+
+/// Check the channel every 10 steps for twice the threshold, returning the
+/// first stall
+fn watch(sim: &Simulator, watchdog: &mut Watchdog, channel: &mut Channel) -> WatchdogStatus {
+    for _ in 0..(2 * THRESHOLD.as_micros() / 10) {
+        let status = watchdog.check(channel, sim).unwrap();
+        if matches!(status, WatchdogStatus::Stalled(_)) {
+            return status;
+        }
+        for _ in 0..10 {
+            sim.step();
+        }
+    }
+    WatchdogStatus::Running
+}
+
+#[test]
+fn idle_rx_line_is_not_a_stall() {
+    let (sim, _uart, dma) = setup();
+    let (mut channel, _) = uart_rx(&sim, &dma, 0, 4);
+    channel.clear_and_active(&dma);
+    let mut watchdog = Watchdog::new(THRESHOLD, StallAction::Report);
+
+    assert_eq!(
+        watch(&sim, &mut watchdog, &mut channel),
+        WatchdogStatus::Running
+    );
+    channel.deactive();
+    assert_eq!(
+        watchdog.check(&mut channel, &sim).unwrap(),
+        WatchdogStatus::Idle
+    );
+}
+
+#[test]
+fn stuck_fifo_is_restarted() {
+    let (sim, uart, dma) = setup();
+    let (mut channel, mem_addr) = uart_rx(&sim, &dma, 0, 4);
+    channel.clear_and_active(&dma);
+    let mut watchdog = Watchdog::new(THRESHOLD, StallAction::Restart);
+
+    sim.inject(Faults {
+        stuck_fifo: 1 << 0,
+        ..Default::default()
+    });
+    uart.push_input(b"ok");
+    let WatchdogStatus::Stalled(stall) = watch(&sim, &mut watchdog, &mut channel) else {
+        panic!("stuck FIFO not detected");
+    };
+    assert!(stall.fifo.full);
+    assert_eq!(stall.transferred, 0);
+    assert!(stall.duration > THRESHOLD);
+    assert_eq!(stall.action, StallAction::Restart);
+
+    // Restarted, the channel receives again once the FIFO works
+    assert!(channel.is_running());
+    sim.inject(Faults::default());
+    uart.push_input(b"!?");
+    channel.wait_complete_with(&sim, THRESHOLD).unwrap();
+    assert_eq!(chars(&sim, mem_addr, 4), b"ok!?");
+}
+
+#[test]
+fn missing_completion_is_reported() {
+    let (sim, uart, dma) = setup();
+    let (mut channel, _) = uart_rx(&sim, &dma, 0, 4);
+    channel.clear_and_active(&dma);
+    let mut watchdog = Watchdog::new(THRESHOLD, StallAction::Abort);
+
+    sim.inject(Faults {
+        lost_completion: 1 << 0,
+        ..Default::default()
+    });
+    uart.push_input(b"full");
+    let WatchdogStatus::Stalled(stall) = watch(&sim, &mut watchdog, &mut channel) else {
+        panic!("missing completion not detected");
+    };
+    assert_eq!(stall.transferred, 16);
+    assert!(stall.fifo.empty);
+
+    // Aborted, the channel is stopped and can be started again
+    assert!(!channel.is_running());
+    assert!(!channel.is_submitted());
+    assert_eq!(channel.transferred().unwrap(), 0);
+}
+
+#[test]
+fn restarts_follow_retry_policy() {
+    let (sim, uart, dma) = setup();
+    let (mut channel, _) = uart_rx(&sim, &dma, 0, 4);
+    channel.clear_and_active(&dma);
+    channel.set_retry_policy(RetryPolicy { max_retries: 1 });
+    let mut watchdog = Watchdog::new(THRESHOLD, StallAction::Restart);
+
+    sim.inject(Faults {
+        stuck_fifo: 1 << 0,
+        ..Default::default()
+    });
+    uart.push_input(b"data");
+    let WatchdogStatus::Stalled(first) = watch(&sim, &mut watchdog, &mut channel) else {
+        panic!("stuck FIFO not detected");
+    };
+    assert_eq!(first.action, StallAction::Restart);
+    assert!(channel.is_running());
+
+    // Still stuck after the restart, the channel is stopped
+    let WatchdogStatus::Stalled(second) = watch(&sim, &mut watchdog, &mut channel) else {
+        panic!("second stall not detected");
+    };
+    assert_eq!(second.action, StallAction::Abort);
+    assert!(!channel.is_running());
+}
+
+#[test]
+fn draining_tx_is_not_a_stall() {
+    let (sim, uart, dma) = setup();
+    // Longer than the threshold at one character per step
+    let msg = [b'x'; 2 * THRESHOLD.as_micros() as usize];
+    let (mut channel, _) = uart_tx(&sim, &dma, 0, &msg);
+    channel.clear_and_active(&dma);
+    let mut watchdog = Watchdog::new(THRESHOLD, StallAction::Report);
+
+    // Checked until the completion, as a driver polling its channels does
+    while !channel.poll_complete() {
+        assert_eq!(
+            watchdog.check(&mut channel, &sim).unwrap(),
+            WatchdogStatus::Running
+        );
+        for _ in 0..10 {
+            sim.step();
+        }
+    }
+    assert!(sim.now() > THRESHOLD);
+    drain(&sim, &uart);
+    assert_eq!(uart.output(), msg);
+}
+
+#[test]
+fn blocked_tx_peripheral_is_a_stall() {
+    let (sim, _uart, dma) = setup();
+    sim.attach(peripheral_ids::UART0_TX, peripheral_ids::UART0_RX, Blocked);
+    let size = 4 * FIFO_DEPTH * 4;
+    let mut channel = dma
+        .prepare_transfer(
+            &DmaChannelConfig {
+                channel: 0,
+                peripheral_id: peripheral_ids::UART0_TX,
+                direction: DmaDirection::MemoryToDevice,
+                timeout_enable: false,
+                timeout_count: 0,
+                irq: true,
+            },
+            &DmaTransfer {
+                mem_addr: sim.alloc(size),
+                dev_addr: UART0_DR,
+                size,
+            },
+        )
+        .unwrap();
+    channel.clear_and_active(&dma);
+    let mut watchdog = Watchdog::new(THRESHOLD, StallAction::Report);
+
+    // The FIFO fills up from memory, then nothing moves
+    let WatchdogStatus::Stalled(stall) = watch(&sim, &mut watchdog, &mut channel) else {
+        panic!("blocked peripheral not detected");
+    };
+    assert!(stall.fifo.full);
+    assert_eq!(stall.transferred, FIFO_DEPTH * 4);
+    assert_eq!(stall.action, StallAction::Report);
+    assert!(channel.is_running());
+}
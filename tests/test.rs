@@ -7,7 +7,7 @@ extern crate bare_test;
 
 #[bare_test::tests]
 mod tests {
-    use core::sync::atomic::AtomicBool;
+    use core::{sync::atomic::AtomicBool, time::Duration};
 
     use super::*;
     use alloc::sync::Arc;
@@ -18,7 +18,7 @@ mod tests {
         mem::iomap,
     };
     use log::{debug, info, trace};
-    use phytium_ddma::{ChannelConfig, DDMA, DmaDirection, peripheral_ids};
+    use phytium_ddma::{ChannelConfig, DDMA, DmaDirection, RawRequest, peripheral_ids};
 
     #[test]
     fn test_dma_memory_to_uart1_tx() {
@@ -30,7 +30,7 @@ mod tests {
 
         debug!("DDMA base address: {:p}, size: {:#x}", base, size);
 
-        let mut dma = DDMA::new(base);
+        let dma = DDMA::new(base);
 
         dma.reset();
 
@@ -61,12 +61,18 @@ mod tests {
             .new_channel(
                 0,
                 ChannelConfig {
-                    slave_id: peripheral_ids::UART1_TX,
-                    direction: DmaDirection::MemoryToDevice,
                     timeout_count: 0x1000,
-                    blk_size: 4,
-                    dev_addr: uart_1_addr as _, // UART1 TX FIFO address (base + 0x00)
-                    irq: true,
+                    ..ChannelConfig::for_request(
+                        // UART1 TX FIFO address (base + 0x00)
+                        RawRequest::new(
+                            peripheral_ids::UART1_TX,
+                            DmaDirection::MemoryToDevice,
+                            uart_1_addr as _,
+                        )
+                        .unwrap(),
+                        4,
+                        true,
+                    )
                 },
             )
             .expect("Failed to create DMA channel 0");
@@ -86,7 +92,9 @@ mod tests {
         .register_builder({
             let done = irq_done.clone();
             move |_irq| {
-                handle.handle_irq();
+                if handle.handle_irq().is_none() {
+                    return IrqHandleResult::Unhandled;
+                }
                 done.store(true, core::sync::atomic::Ordering::SeqCst);
                 // info!("DMA interrupt handled");
                 IrqHandleResult::Handled
@@ -112,28 +120,9 @@ mod tests {
         // Debug: Check state after DMA enable
         dma.debug_status(channel.index());
 
-        // Wait for transfer completion (polling mode for this test)
-        let mut timeout = 100000; // Increase timeout
-        while !irq_done.load(core::sync::atomic::Ordering::SeqCst) && timeout > 0 {
-            timeout -= 1;
-
-            // Add periodic status check
-            if timeout % 10000 == 0 {
-                debug!("DMA transfer in progress, timeout remaining: {}", timeout);
-                debug!("Channel running: {}", channel.is_running());
-                if timeout % 50000 == 0 {
-                    channel.debug_registers();
-                    dma.debug_status(channel.index());
-                }
-            }
-            // Small delay to prevent busy waiting
-            for _ in 0..1000 {
-                core::hint::spin_loop();
-            }
-        }
-
-        if timeout == 0 {
-            info!("DMA transfer timed out");
+        // Wait for transfer completion
+        if let Err(e) = channel.wait_complete(Duration::from_secs(1)) {
+            info!("DMA transfer timed out: {:?}", e);
 
             // Final debug output
             info!("=== Final Status Debug ===");
@@ -143,12 +132,32 @@ mod tests {
             return;
         }
 
+        debug!(
+            "DMA interrupt received: {}",
+            irq_done.load(core::sync::atomic::Ordering::SeqCst)
+        );
+
         // Clear the completion status
         dma.clear_transfer_complete(channel.index());
 
         info!("DMA transfer completed successfully! Character 'A' transferred to UART1 TX");
     }
 
+    #[test]
+    fn test_self_test() {
+        let (addr, size, _irq_info) = get_ddma0();
+
+        let base = iomap(addr.into(), size);
+
+        let dma = DDMA::new(base);
+
+        let report = dma.self_test().unwrap();
+
+        info!("DDMA self-test report: {:?}", report);
+
+        assert!(report.passed(), "DDMA self-test failed");
+    }
+
     fn get_ddma0() -> (usize, usize, IrqInfo) {
         let PlatformInfoKind::DeviceTree(fdt) = &global_val().platform_info;
         let fdt = fdt.get();